@@ -1,14 +1,33 @@
 extern crate proc_macro;
 use proc_macro::{TokenStream};
 use proc_macro2::{Span};
-use quote::{quote};
-use syn::{parse_macro_input, ItemImpl, ImplItem, ImplItemMethod, ItemTrait, TraitItem, Ident, Block, Visibility, VisPublic, token::Pub};
-//use syn::{ReturnType};
+use quote::{quote, format_ident};
+use syn::{parse_macro_input, ItemImpl, ImplItem, ImplItemMethod, ItemTrait, TraitItem, TraitItemMethod, Ident, Block, Visibility, VisPublic, token::Pub};
+use syn::{FnArg, Pat, ReturnType, Type, GenericArgument, PathArguments, AttributeArgs, NestedMeta, Meta, Lit};
 
 /// a macro to generate an empty blueprint just to get the stub functions for inter-blueprint calls
 /// avoids manually specifying an ABI, and allows a concise definition using trait syntax
+///
+/// for a trait `Foo` it also generates (for the methods taking `self`):
+/// * `FooApi` - the trait itself, so code can be written against either of the implementations below
+/// * `FooClient` - a typed client for calling an existing component, create it `From` a `Component` or `Address`
+/// * `MockFoo` - an in-memory implementation with one replaceable closure per method, for unit tests
+/// * `FooClient::abi(package_address)` - the JSON ABI for the blueprint, as expected by `import!`
+///
+/// options, all optional:
+/// * `scrypto = "0.4"` - generate a client for scrypto v0.4 packages: it wraps a `ComponentAddress`, can be kept in
+///   component state, and no stub blueprint is generated.  The default targets scrypto v0.3 and calls through `Component`
+/// * `package_address = "01..."` - also generate `FooClient::ABI`, the JSON ABI as a constant for that package
+///
+/// ```ignore
+/// #[blueprint_stub(scrypto = "0.4", package_address = "01c7adee40dd9a16ae290272d0e99835ad5c5e679941d3fb28e608")]
+/// pub trait Staking {
+///     fn new_user(&mut self) -> Bucket;
+/// }
+/// ```
 #[proc_macro_attribute]
-pub fn blueprint_stub(_metadata: TokenStream, input: TokenStream) -> TokenStream {
+pub fn blueprint_stub(metadata: TokenStream, input: TokenStream) -> TokenStream {
+    let options = StubOptions::parse(parse_macro_input!(metadata as AttributeArgs));
     // parse the trait
     let mut input = parse_macro_input!(input as ItemTrait);
     // grap the identifier
@@ -89,6 +108,17 @@ pub fn blueprint_stub(_metadata: TokenStream, input: TokenStream) -> TokenStream
         }
     }
 
+    let methods: Vec<&TraitItemMethod> = input.items.iter().filter_map(|item| match item {
+        TraitItem::Method(method) => Some(method),
+        _ => None
+    }).collect();
+    let client = generate_client(&ident, &methods, &options);
+
+    // v0.4 packages call other packages directly, the client is all they need
+    if options.scrypto == Scrypto::V0_4 {
+        return TokenStream::from(client);
+    }
+
     // create the final output
     // a blueprint! with the geenerated impl, but all inside a module so the code is not callable
     // then reexport only the stubs
@@ -103,5 +133,461 @@ pub fn blueprint_stub(_metadata: TokenStream, input: TokenStream) -> TokenStream
         }
         // reexport the stub only
         pub use #mod_name::#ident;
+
+        #client
     })
-}
\ No newline at end of file
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Scrypto {
+    V0_3,
+    V0_4,
+}
+
+/// the options of `blueprint_stub`
+struct StubOptions {
+    scrypto: Scrypto,
+    package_address: Option<String>,
+}
+
+impl StubOptions {
+    fn parse(args: AttributeArgs) -> Self {
+        let mut options = StubOptions { scrypto: Scrypto::V0_3, package_address: None };
+        for arg in args {
+            let (name, value) = match arg {
+                NestedMeta::Meta(Meta::NameValue(name_value)) => match name_value.lit {
+                    Lit::Str(value) => (name_value.path, value.value()),
+                    _ => panic!("blueprint_stub options take string values")
+                },
+                _ => panic!("blueprint_stub options are `name = \"value\"`")
+            };
+            if name.is_ident("scrypto") {
+                options.scrypto = match value.as_str() {
+                    "0.3" => Scrypto::V0_3,
+                    "0.4" => Scrypto::V0_4,
+                    _ => panic!("blueprint_stub supports scrypto \"0.3\" and \"0.4\"")
+                };
+            } else if name.is_ident("package_address") {
+                options.package_address = Some(value);
+            } else {
+                panic!("unknown blueprint_stub option");
+            }
+        }
+        options
+    }
+}
+
+/// one method of the trait, with the argument names normalized so they can be reused in generated code
+struct MethodInfo {
+    ident: Ident,
+    receiver: Option<proc_macro2::TokenStream>, // `&self` or `&mut self`, None for a function
+    mutable: bool,
+    scrypto: Scrypto,
+    arg_idents: Vec<Ident>,
+    arg_types: Vec<Type>,
+    output: proc_macro2::TokenStream,
+    output_abi: String,
+}
+
+fn method_info(method: &TraitItemMethod, scrypto: Scrypto) -> MethodInfo {
+    let mut receiver = None;
+    let mut mutable = false;
+    let mut arg_idents = Vec::new();
+    let mut arg_types = Vec::new();
+    for (i, input) in method.sig.inputs.iter().enumerate() {
+        match input {
+            FnArg::Receiver(r) => {
+                mutable = r.mutability.is_some();
+                receiver = Some(quote! { #r });
+            },
+            FnArg::Typed(pat_type) => {
+                // keep the name if it's a simple identifier, otherwise make one up
+                let arg_ident = match &*pat_type.pat {
+                    Pat::Ident(pat_ident) => pat_ident.ident.clone(),
+                    _ => format_ident!("arg{}", i)
+                };
+                arg_idents.push(arg_ident);
+                arg_types.push((*pat_type.ty).clone());
+            }
+        }
+    }
+    let (output, output_abi) = match &method.sig.output {
+        ReturnType::Default => (quote! { () }, type_abi_unit()),
+        ReturnType::Type(_, ty) => (quote! { #ty }, type_abi(ty, scrypto))
+    };
+    MethodInfo {
+        ident: method.sig.ident.clone(),
+        receiver,
+        mutable,
+        scrypto,
+        arg_idents,
+        arg_types,
+        output,
+        output_abi,
+    }
+}
+
+fn generate_client(ident: &Ident, methods: &[&TraitItemMethod], options: &StubOptions) -> proc_macro2::TokenStream {
+    let api = format_ident!("{}Api", ident);
+    let client = format_ident!("{}Client", ident);
+    let mock = format_ident!("Mock{}", ident);
+
+    let infos: Vec<MethodInfo> = methods.iter().map(|method| method_info(method, options.scrypto)).collect();
+    // only methods can be called on a component, functions only show up in the ABI
+    let component_methods: Vec<&MethodInfo> = infos.iter().filter(|info| info.receiver.is_some()).collect();
+
+    let api_fns = component_methods.iter().map(|info| {
+        let MethodInfo { ident, receiver, arg_idents, arg_types, output, .. } = info;
+        quote! {
+            fn #ident(#receiver, #(#arg_idents: #arg_types),*) -> #output;
+        }
+    });
+
+    let client_fns = component_methods.iter().map(|info| {
+        let MethodInfo { ident, arg_idents, arg_types, output, .. } = info;
+        let name = ident.to_string();
+        match options.scrypto {
+            Scrypto::V0_3 => quote! {
+                pub fn #ident(&self, #(#arg_idents: #arg_types),*) -> #output {
+                    self.component.call::<#output>(#name, ::scrypto::args!(#(#arg_idents),*))
+                }
+            },
+            Scrypto::V0_4 => quote! {
+                pub fn #ident(&self, #(#arg_idents: #arg_types),*) -> #output {
+                    ::scrypto::borrow_component!(self.component).call::<#output>(#name, ::scrypto::args!(#(#arg_idents),*))
+                }
+            }
+        }
+    });
+
+    let client_api_fns = component_methods.iter().map(|info| {
+        let MethodInfo { ident, receiver, arg_idents, arg_types, output, .. } = info;
+        quote! {
+            fn #ident(#receiver, #(#arg_idents: #arg_types),*) -> #output {
+                #client::#ident(self, #(#arg_idents),*)
+            }
+        }
+    });
+
+    let mock_fields = component_methods.iter().map(|info| {
+        let MethodInfo { ident, arg_types, output, .. } = info;
+        quote! {
+            pub #ident: Box<dyn Fn(#(#arg_types),*) -> #output>,
+        }
+    });
+
+    let mock_defaults = component_methods.iter().map(|info| {
+        let MethodInfo { ident, arg_types, .. } = info;
+        let message = format!("{}::{} is not mocked", mock, ident);
+        quote! {
+            #ident: Box::new(|#(_: #arg_types),*| panic!(#message)),
+        }
+    });
+
+    let mock_api_fns = component_methods.iter().map(|info| {
+        let MethodInfo { ident, receiver, arg_idents, arg_types, output, .. } = info;
+        quote! {
+            fn #ident(#receiver, #(#arg_idents: #arg_types),*) -> #output {
+                (self.#ident)(#(#arg_idents),*)
+            }
+        }
+    });
+
+    let abi_template = abi_template(&ident.to_string(), &infos);
+    // the ABI of the published package, as a literal so it can be used at compile time
+    let abi_const = options.package_address.as_ref().map(|package_address| {
+        let abi = abi_template.replace("{package_address}", package_address);
+        quote! {
+            /// the JSON ABI of the blueprint in its published package
+            pub const ABI: &'static str = #abi;
+        }
+    });
+
+    let client_struct = match options.scrypto {
+        Scrypto::V0_3 => quote! {
+            /// typed client for calling the methods of an existing component
+            #[derive(Clone)]
+            pub struct #client {
+                component: ::scrypto::prelude::Component,
+            }
+
+            impl From<::scrypto::prelude::Component> for #client {
+                fn from(component: ::scrypto::prelude::Component) -> Self {
+                    Self { component }
+                }
+            }
+
+            impl From<::scrypto::prelude::Address> for #client {
+                fn from(address: ::scrypto::prelude::Address) -> Self {
+                    Self { component: address.into() }
+                }
+            }
+
+            #[allow(unused)]
+            impl #client {
+                pub fn component(&self) -> ::scrypto::prelude::Component {
+                    self.component.clone()
+                }
+            }
+        },
+        Scrypto::V0_4 => quote! {
+            /// typed client for calling the methods of an existing component, it can be kept in component state
+            #[derive(::sbor::TypeId, ::sbor::Encode, ::sbor::Decode, ::sbor::Describe, Clone, Debug, PartialEq)]
+            pub struct #client {
+                component: ::scrypto::prelude::ComponentAddress,
+            }
+
+            impl From<::scrypto::prelude::ComponentAddress> for #client {
+                fn from(component: ::scrypto::prelude::ComponentAddress) -> Self {
+                    Self { component }
+                }
+            }
+
+            #[allow(unused)]
+            impl #client {
+                pub fn component(&self) -> ::scrypto::prelude::ComponentAddress {
+                    self.component
+                }
+            }
+        }
+    };
+
+    quote! {
+        /// the methods of the blueprint, implemented by the client and the mock
+        pub trait #api {
+            #(#api_fns)*
+        }
+
+        #client_struct
+
+        #[allow(unused)]
+        impl #client {
+            /// the ABI with a `{package_address}` placeholder
+            pub const ABI_TEMPLATE: &'static str = #abi_template;
+
+            #abi_const
+
+            /// the JSON ABI of the blueprint published at `package_address`, as expected by `import!`
+            pub fn abi(package_address: &str) -> String {
+                Self::ABI_TEMPLATE.replace("{package_address}", package_address)
+            }
+
+            #(#client_fns)*
+        }
+
+        impl #api for #client {
+            #(#client_api_fns)*
+        }
+
+        /// in-memory implementation for unit tests, replace the closures for the methods under test
+        pub struct #mock {
+            #(#mock_fields)*
+        }
+
+        impl Default for #mock {
+            fn default() -> Self {
+                Self {
+                    #(#mock_defaults)*
+                }
+            }
+        }
+
+        impl #api for #mock {
+            #(#mock_api_fns)*
+        }
+    }
+}
+
+fn abi_template(blueprint_name: &str, infos: &[MethodInfo]) -> String {
+    let entry = |info: &MethodInfo| {
+        let inputs: Vec<String> = info.arg_types.iter().map(|ty| type_abi(ty, info.scrypto)).collect();
+        let mutability = match info.receiver {
+            Some(_) if info.mutable => r#""mutability":"Mutable","#,
+            Some(_) => r#""mutability":"Immutable","#,
+            None => ""
+        };
+        format!(r#"{{"name":"{}",{}"inputs":[{}],"output":{}}}"#, info.ident, mutability, inputs.join(","), info.output_abi)
+    };
+    let functions: Vec<String> = infos.iter().filter(|info| info.receiver.is_none()).map(entry).collect();
+    let methods: Vec<String> = infos.iter().filter(|info| info.receiver.is_some()).map(entry).collect();
+    format!(
+        r#"{{"package_address":"{{package_address}}","blueprint_name":"{}","functions":[{}],"methods":[{}]}}"#,
+        blueprint_name,
+        functions.join(","),
+        methods.join(",")
+    )
+}
+
+fn type_abi_unit() -> String {
+    r#"{"type":"Unit"}"#.to_owned()
+}
+
+/// sbor type description of a rust type, in the JSON format of an ABI
+/// anything that isn't a primitive or a std container is assumed to be a scrypto custom type with the same name
+fn type_abi(ty: &Type, scrypto: Scrypto) -> String {
+    match ty {
+        Type::Reference(reference) => type_abi(&reference.elem, scrypto),
+        Type::Paren(paren) => type_abi(&paren.elem, scrypto),
+        Type::Tuple(tuple) if tuple.elems.is_empty() => type_abi_unit(),
+        Type::Tuple(tuple) => {
+            let elements: Vec<String> = tuple.elems.iter().map(|ty| type_abi(ty, scrypto)).collect();
+            format!(r#"{{"type":"Tuple","elements":[{}]}}"#, elements.join(","))
+        },
+        Type::Path(type_path) => {
+            let segment = type_path.path.segments.last().expect("empty type path");
+            let generics: Vec<&Type> = match &segment.arguments {
+                PathArguments::AngleBracketed(args) => args.args.iter().filter_map(|arg| match arg {
+                    GenericArgument::Type(ty) => Some(ty),
+                    _ => None
+                }).collect(),
+                _ => Vec::new()
+            };
+            let name = segment.ident.to_string();
+            match (name.as_str(), generics.as_slice()) {
+                ("bool", []) => r#"{"type":"Bool"}"#.to_owned(),
+                ("i8", []) | ("i16", []) | ("i32", []) | ("i64", []) | ("i128", []) |
+                ("u8", []) | ("u16", []) | ("u32", []) | ("u64", []) | ("u128", []) => {
+                    format!(r#"{{"type":"{}"}}"#, name.to_uppercase())
+                },
+                ("String", []) => r#"{"type":"String"}"#.to_owned(),
+                ("Box", [inner]) => type_abi(inner, scrypto),
+                ("Option", [inner]) => format!(r#"{{"type":"Option","value":{}}}"#, type_abi(inner, scrypto)),
+                ("Vec", [inner]) | ("HashSet", [inner]) | ("BTreeSet", [inner]) => {
+                    format!(r#"{{"type":"{}","element":{}}}"#, name, type_abi(inner, scrypto))
+                },
+                ("HashMap", [key, value]) | ("BTreeMap", [key, value]) => {
+                    format!(r#"{{"type":"{}","key":{},"value":{}}}"#, name, type_abi(key, scrypto), type_abi(value, scrypto))
+                },
+                // scrypto_statictypes wrappers are plain resource containers in the ABI
+                ("BucketOf", _) => custom_type_abi("Bucket"),
+                ("BucketRefOf", _) if scrypto == Scrypto::V0_4 => custom_type_abi("Proof"),
+                ("BucketRefOf", _) => custom_type_abi("BucketRef"),
+                ("VaultOf", _) => custom_type_abi("Vault"),
+                ("ResourceOf", _) if scrypto == Scrypto::V0_4 => custom_type_abi("ResourceAddress"),
+                ("ResourceOf", _) => custom_type_abi("ResourceDef"),
+                _ => custom_type_abi(&name)
+            }
+        },
+        _ => panic!("unsupported type in blueprint_stub")
+    }
+}
+
+fn custom_type_abi(name: &str) -> String {
+    format!(r#"{{"type":"Custom","name":"{}","generics":[]}}"#, name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn abi_of(ty: &str) -> String {
+        type_abi(&syn::parse_str::<Type>(ty).unwrap(), Scrypto::V0_3)
+    }
+
+    #[test]
+    fn test_type_abi() {
+        assert_eq!(abi_of("u32"), r#"{"type":"U32"}"#);
+        assert_eq!(abi_of("()"), r#"{"type":"Unit"}"#);
+        assert_eq!(abi_of("Decimal"), r#"{"type":"Custom","name":"Decimal","generics":[]}"#);
+        assert_eq!(abi_of("BucketOf<RESERVE>"), r#"{"type":"Custom","name":"Bucket","generics":[]}"#);
+        assert_eq!(abi_of("Option<String>"), r#"{"type":"Option","value":{"type":"String"}}"#);
+        assert_eq!(abi_of("Vec<(bool, u8)>"), r#"{"type":"Vec","element":{"type":"Tuple","elements":[{"type":"Bool"},{"type":"U8"}]}}"#);
+    }
+
+    #[test]
+    fn test_generated_client_parses() {
+        let item: ItemTrait = syn::parse_str(r#"
+            pub trait Counter {
+                fn new() -> Component;
+                fn add(&mut self, amount: u32);
+                fn get(&self) -> u32;
+            }
+        "#).unwrap();
+        let methods: Vec<&TraitItemMethod> = item.items.iter().filter_map(|item| match item {
+            TraitItem::Method(method) => Some(method),
+            _ => None
+        }).collect();
+        let options = StubOptions { scrypto: Scrypto::V0_3, package_address: None };
+        let generated = generate_client(&item.ident, &methods, &options);
+        let file: syn::File = syn::parse2(generated).unwrap();
+        let names: Vec<String> = file.items.iter().filter_map(|item| match item {
+            syn::Item::Trait(item) => Some(item.ident.to_string()),
+            syn::Item::Struct(item) => Some(item.ident.to_string()),
+            _ => None
+        }).collect();
+        assert_eq!(names, vec!["CounterApi", "CounterClient", "MockCounter"]);
+    }
+
+    #[test]
+    fn test_abi_template() {
+        let item: ItemTrait = syn::parse_str(r#"
+            pub trait Staking {
+                fn new(tokens: Bucket) -> Component;
+                fn new_user(&mut self) -> Bucket;
+                fn get_amount(&self, user: BucketRef) -> Decimal;
+            }
+        "#).unwrap();
+        let infos: Vec<MethodInfo> = item.items.iter().filter_map(|item| match item {
+            TraitItem::Method(method) => Some(method_info(method, Scrypto::V0_3)),
+            _ => None
+        }).collect();
+        let abi = abi_template("Staking", &infos);
+        assert_eq!(abi, concat!(
+            r#"{"package_address":"{package_address}","blueprint_name":"Staking","#,
+            r#""functions":[{"name":"new","inputs":[{"type":"Custom","name":"Bucket","generics":[]}],"output":{"type":"Custom","name":"Component","generics":[]}}],"#,
+            r#""methods":[{"name":"new_user","mutability":"Mutable","inputs":[],"output":{"type":"Custom","name":"Bucket","generics":[]}},"#,
+            r#"{"name":"get_amount","mutability":"Immutable","inputs":[{"type":"Custom","name":"BucketRef","generics":[]}],"output":{"type":"Custom","name":"Decimal","generics":[]}}]}"#
+        ));
+    }
+
+    #[test]
+    fn test_options() {
+        let options = StubOptions::parse(vec![
+            syn::parse_quote!(scrypto = "0.4"),
+            syn::parse_quote!(package_address = "01abcd"),
+        ]);
+        assert_eq!(options.scrypto, Scrypto::V0_4);
+        assert_eq!(options.package_address, Some("01abcd".to_owned()));
+
+        let options = StubOptions::parse(Vec::new());
+        assert_eq!(options.scrypto, Scrypto::V0_3);
+        assert_eq!(options.package_address, None);
+    }
+
+    #[test]
+    fn test_v0_4_client() {
+        let item: ItemTrait = syn::parse_str(r#"
+            pub trait Staking {
+                fn new(rewards: Bucket) -> ComponentAddress;
+                fn stake(&mut self, staking: Bucket, auth: BucketRefOf<STAKE>);
+            }
+        "#).unwrap();
+        let methods: Vec<&TraitItemMethod> = item.items.iter().filter_map(|item| match item {
+            TraitItem::Method(method) => Some(method),
+            _ => None
+        }).collect();
+        let options = StubOptions { scrypto: Scrypto::V0_4, package_address: Some("01abcd".to_owned()) };
+        let generated = generate_client(&item.ident, &methods, &options).to_string();
+        assert!(generated.contains("ComponentAddress"));
+        assert!(generated.contains("borrow_component"));
+        assert!(!generated.contains("prelude :: Component ,"));
+
+        // the ABI is a literal with the package address and the v0.4 types
+        let file: syn::File = syn::parse_str(&generated).unwrap();
+        let abi = file.items.iter().find_map(|item| match item {
+            syn::Item::Impl(item) => item.items.iter().find_map(|item| match item {
+                ImplItem::Const(item) if item.ident == "ABI" => match &item.expr {
+                    syn::Expr::Lit(syn::ExprLit { lit: Lit::Str(abi), .. }) => Some(abi.value()),
+                    _ => None
+                },
+                _ => None
+            }),
+            _ => None
+        }).unwrap();
+        assert_eq!(abi, concat!(
+            r#"{"package_address":"01abcd","blueprint_name":"Staking","#,
+            r#""functions":[{"name":"new","inputs":[{"type":"Custom","name":"Bucket","generics":[]}],"output":{"type":"Custom","name":"ComponentAddress","generics":[]}}],"#,
+            r#""methods":[{"name":"stake","mutability":"Mutable","inputs":[{"type":"Custom","name":"Bucket","generics":[]},{"type":"Custom","name":"Proof","generics":[]}],"output":{"type":"Unit"}}]}"#
+        ));
+    }
+}
@@ -21,3 +21,39 @@ pub trait BondingCurve {
     fn get_initial_supply(&self, collateral_amount: Decimal) -> Decimal;
     fn get_price(&self, reserve_amount: Decimal, supply_amount: Decimal) -> Decimal;
 }
+
+// -------- Testing
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // anything written against BondingCurveApi can be unit tested without a ledger
+    fn spread(curve: &dyn BondingCurveApi, amount: Decimal, reserve: Decimal, supply: Decimal) -> Decimal {
+        let minted = curve.get_mint_amount(amount, reserve, supply);
+        let returned = curve.get_return_amount(minted, reserve + amount, supply + minted);
+        amount - returned
+    }
+
+    #[test]
+    fn test_mock_curve() {
+        let mut curve = MockBondingCurve::default();
+        curve.get_mint_amount = Box::new(|collateral_amount, _, _| collateral_amount * 2);
+        curve.get_return_amount = Box::new(|continuous_amount, _, _| continuous_amount / 4);
+        assert_eq!(spread(&curve, 100.into(), 0.into(), 0.into()), 50.into());
+    }
+
+    #[test]
+    #[should_panic(expected = "MockBondingCurve::get_price is not mocked")]
+    fn test_mock_curve_unmocked() {
+        let curve = MockBondingCurve::default();
+        curve.get_price(1.into(), 1.into());
+    }
+
+    #[test]
+    fn test_abi() {
+        let abi = BondingCurveClient::abi("01abcd");
+        assert!(abi.starts_with(r#"{"package_address":"01abcd","blueprint_name":"BondingCurve","#));
+        assert!(abi.contains(r#"{"name":"get_price","mutability":"Immutable","#));
+    }
+}
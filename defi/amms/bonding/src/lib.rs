@@ -4,14 +4,36 @@
 //! bonding curve defined by a plugable second component.  It will instantiate a component from the `RatioBondingCurve`
 //! blueprint if no other curve component is provided.
 //!
+//! A `BondingAMM` can optionally take an entry (buy) and exit (sell) fee in the reserve resource.  Each fee either stays
+//! in the reserve or goes to a beneficiary vault which the holder of the owner badge can withdraw from.  The quote methods
+//! already have the fees taken out so they stay exact.
+//!
+//! A curve can also be bootstrapped with a virtual reserve and virtual supply instead of seeding real collateral.  They
+//! shape the starting price and are added to the real amounts for every `BondingCurve` call, so a token can launch from
+//! zero real reserve without self-minting.
+//!
+//! Besides the plain `get_buy_quote_amount` and `get_sell_quote_amount`, `get_buy_quote` and `get_sell_quote` return a
+//! `Quote` with the average execution price, the spot price before and after, and the price impact, for any curve.
+//!
+//! The curve is not fixed forever: every constructor returns an owner badge, whose holder can propose a replacement curve
+//! component, which is only accepted if it is solvent for the current reserve/supply, and anyone can switch to it once a
+//! notice period (in epochs) is over.
+//!
 //! There is also an extrememly simple `BasicBondingCurve` blueprint (more like a flat line) which could be used to implement
 //! a simple "wrapped" or "virtual" token from another.  Or it's a a good template for making your own curve component.
 //!
 //! Some useful utilities are included too.  An internal crate is used to provide the `blueprint_stub` macro which
-//! automates creating stub functions from a trait so calling another component is ergonomic.  Also included and
+//! automates creating stub functions from a trait so calling another component is ergonomic.  From the same trait it
+//! generates a typed client (`BondingCurveClient`), a mock for unit tests (`MockBondingCurve`) and the JSON ABI.  With
+//! `#[blueprint_stub(scrypto = "0.4", package_address = "...")]` it targets scrypto v0.4 packages and the ABI is a constant, the
+//! no-loss lottery calls `Staking` that way.  Also included and
 //! used for the `RatioBondingCurve` is a reusable arbitrary precision number implmentation that converts to/from Decimal
 //! It is precise but not yet optimized.  Bounded (BigInt) or unbounded (BigRational) precision is configurable with a feature flag.
 //!
+//! Since that only supports rational curve weights, there is also a deterministic fixed-point `math` module providing `ln`,
+//! `exp`, `pow` with any real exponent and `sqrt` at Decimal precision (with documented error bounds).  The
+//! `WeightedBondingCurve` blueprint uses it to accept any reserve ratio in (0, 1] without blowing up the number sizes.
+//!
 //! # Bonuses:
 //!
 //! * This package is well tested (though not completely) with both unit and integration tests.  Integration
@@ -47,7 +69,12 @@
 mod basic_curve; // a simple flat "curve" 1:1 implementation as a reference
 mod bonding_curve;
 mod default_curve; // a complete non-production bonding curve implementation parametrizable by "curve weight" aka "reserve ratio".  Max precision within Decimal. (ie. precise, but unoptimized)
+pub mod math; // deterministic fixed-point ln/exp/pow/sqrt at Decimal precision used in weighted_curve
 mod number; // arbitrary precision math used in default_curve // the trait for cross-blueprint calls for plugable curve math
+mod quote; // structured quote results with price impact
+mod weighted_curve; // like default_curve, but with any Decimal "curve weight" using the fixed-point math
+
+pub use quote::Quote;
 
 use scrypto::prelude::*;
 use scrypto_statictypes::prelude::*; // Use https://github.com/devmannic/scrypto_statictypes
@@ -55,6 +82,10 @@ use scrypto_statictypes::prelude::*; // Use https://github.com/devmannic/scrypto
 declare_resource!(RESERVE); // resource type used for the reserve pool, collateral for buys
 declare_resource!(CONTINUOUS); // resource type minted/burned by the bonding curve
 declare_resource!(AUTH); // resource type for authority (badges) for mint/burn and authentication
+declare_resource!(OWNER); // resource type for the owner badge which may withdraw collected fees and migrate the curve
+
+// minimum number of epochs between proposing a new curve and switching to it, the owner can only make it longer
+const DEFAULT_MIGRATION_NOTICE_EPOCHS: u64 = 100;
 
 blueprint! {
     struct BondingAMM {
@@ -62,6 +93,16 @@ blueprint! {
         continuous: VaultOf<CONTINUOUS>,
         continuous_auth: VaultOf<AUTH>,
         bonding_curve: Component, // plugable, does the math
+        buy_fee_percent: Decimal,  // taken from the RESERVE sent in to `buy`
+        sell_fee_percent: Decimal, // taken from the RESERVE returned by `sell`
+        buy_fee_to_reserve: bool,  // true: the buy fee stays in the reserve, false: it goes to the beneficiary `fees` vault
+        sell_fee_to_reserve: bool, // true: the sell fee stays in the reserve, false: it goes to the beneficiary `fees` vault
+        fees: VaultOf<RESERVE>,    // collected fees, withdrawable with the owner badge
+        owner_badge: Address, // withdraws the beneficiary fees and migrates the curve
+        virtual_reserve: Decimal, // added to the real reserve for every curve calculation, shapes the starting price
+        virtual_supply: Decimal,  // added to the real supply for every curve calculation, shapes the starting price
+        pending_curve: Option<(Component, u64)>, // proposed replacement curve and the epoch it can be switched to
+        migration_notice_epochs: u64,
     }
 
     impl BondingAMM {
@@ -70,7 +111,7 @@ blueprint! {
             initial_reserve: BucketOf<RESERVE>,
             continuous_name: String,
             continuous_symbol: String,
-        ) -> (Component, BucketOf<CONTINUOUS>) {
+        ) -> (Component, BucketOf<CONTINUOUS>, BucketOf<OWNER>) {
             BondingAMM::new(initial_reserve, continuous_name, continuous_symbol, None)
         }
 
@@ -80,7 +121,7 @@ blueprint! {
             continuous_name: String,
             continuous_symbol: String,
             bonding_curve: Address,
-        ) -> (Component, BucketOf<CONTINUOUS>) {
+        ) -> (Component, BucketOf<CONTINUOUS>, BucketOf<OWNER>) {
             BondingAMM::new(
                 initial_reserve,
                 continuous_name,
@@ -89,26 +130,163 @@ blueprint! {
             )
         }
 
+        // Convenient Constructor with default curve and fees - also makes it easy to call from integration tests
+        pub fn new_default_with_fees(
+            initial_reserve: BucketOf<RESERVE>,
+            continuous_name: String,
+            continuous_symbol: String,
+            buy_fee_percent: Decimal,
+            sell_fee_percent: Decimal,
+            buy_fee_to_reserve: bool,
+            sell_fee_to_reserve: bool,
+        ) -> (Component, BucketOf<CONTINUOUS>, BucketOf<OWNER>) {
+            BondingAMM::new_with_fees(
+                initial_reserve,
+                continuous_name,
+                continuous_symbol,
+                None,
+                buy_fee_percent,
+                sell_fee_percent,
+                buy_fee_to_reserve,
+                sell_fee_to_reserve,
+            )
+        }
+
         // Main constructor easily called from other blueprints
+        // The returned owner badge can migrate the curve (see propose_curve)
         pub fn new(
             initial_reserve: BucketOf<RESERVE>,
             continuous_name: String,
             continuous_symbol: String,
             bonding_curve: Option<Component>,
-        ) -> (Component, BucketOf<CONTINUOUS>) {
-            // initial_reserve cannot be empty
-            assert!(!initial_reserve.is_empty());
+        ) -> (Component, BucketOf<CONTINUOUS>, BucketOf<OWNER>) {
+            BondingAMM::instantiate_amm(
+                initial_reserve,
+                continuous_name,
+                continuous_symbol,
+                bonding_curve,
+                None,
+                None,
+            )
+        }
+
+        // Constructor with entry (buy) and exit (sell) fees taken in RESERVE
+        // Each fee either stays in the reserve (raising the price for all holders) or goes to the beneficiary vault
+        // which can be emptied with the returned owner badge
+        pub fn new_with_fees(
+            initial_reserve: BucketOf<RESERVE>,
+            continuous_name: String,
+            continuous_symbol: String,
+            bonding_curve: Option<Component>,
+            buy_fee_percent: Decimal,
+            sell_fee_percent: Decimal,
+            buy_fee_to_reserve: bool,
+            sell_fee_to_reserve: bool,
+        ) -> (Component, BucketOf<CONTINUOUS>, BucketOf<OWNER>) {
+            BondingAMM::instantiate_amm(
+                initial_reserve,
+                continuous_name,
+                continuous_symbol,
+                bonding_curve,
+                Some((
+                    buy_fee_percent,
+                    sell_fee_percent,
+                    buy_fee_to_reserve,
+                    sell_fee_to_reserve,
+                )),
+                None,
+            )
+        }
+
+        // Convenient Constructor with default curve and virtual reserve/supply - also makes it easy to call from integration tests
+        pub fn new_default_with_virtual(
+            initial_reserve: BucketOf<RESERVE>,
+            continuous_name: String,
+            continuous_symbol: String,
+            virtual_reserve: Decimal,
+            virtual_supply: Decimal,
+        ) -> (Component, BucketOf<CONTINUOUS>, BucketOf<OWNER>) {
+            BondingAMM::new_with_virtual(
+                initial_reserve,
+                continuous_name,
+                continuous_symbol,
+                None,
+                virtual_reserve,
+                virtual_supply,
+            )
+        }
+
+        // Constructor bootstrapping the curve with a virtual reserve and supply which are never held or minted
+        // The starting price is the curve price at (virtual_reserve, virtual_supply) so the initial_reserve may be empty
+        // Any initial_reserve is used to buy at that price, there is no self-minting of an initial supply
+        pub fn new_with_virtual(
+            initial_reserve: BucketOf<RESERVE>,
+            continuous_name: String,
+            continuous_symbol: String,
+            bonding_curve: Option<Component>,
+            virtual_reserve: Decimal,
+            virtual_supply: Decimal,
+        ) -> (Component, BucketOf<CONTINUOUS>, BucketOf<OWNER>) {
+            BondingAMM::instantiate_amm(
+                initial_reserve,
+                continuous_name,
+                continuous_symbol,
+                bonding_curve,
+                None,
+                Some((virtual_reserve, virtual_supply)),
+            )
+        }
+
+        // shared by all constructors, fees are (buy_fee_percent, sell_fee_percent, buy_fee_to_reserve, sell_fee_to_reserve)
+        // and virtual_amounts are (virtual_reserve, virtual_supply)
+        fn instantiate_amm(
+            mut initial_reserve: BucketOf<RESERVE>,
+            continuous_name: String,
+            continuous_symbol: String,
+            bonding_curve: Option<Component>,
+            fees: Option<(Decimal, Decimal, bool, bool)>,
+            virtual_amounts: Option<(Decimal, Decimal)>,
+        ) -> (Component, BucketOf<CONTINUOUS>, BucketOf<OWNER>) {
+            let (virtual_reserve, virtual_supply) =
+                virtual_amounts.unwrap_or((Decimal::zero(), Decimal::zero()));
+            assert!(
+                !virtual_reserve.is_negative() && !virtual_supply.is_negative(),
+                "virtual reserve and supply cannot be negative"
+            );
+            assert!(
+                virtual_reserve.is_zero() == virtual_supply.is_zero(),
+                "virtual reserve and supply must both be zero or both be positive"
+            );
+
+            // initial_reserve cannot be empty, unless the virtual reserve is there to price the curve
+            assert!(!initial_reserve.is_empty() || !virtual_reserve.is_zero());
+
+            let (buy_fee_percent, sell_fee_percent, buy_fee_to_reserve, sell_fee_to_reserve) =
+                fees.unwrap_or((Decimal::zero(), Decimal::zero(), true, true));
+            assert!(
+                !buy_fee_percent.is_negative() && buy_fee_percent < 100.into(),
+                "buy fee must be in the range [0, 100)"
+            );
+            assert!(
+                !sell_fee_percent.is_negative() && sell_fee_percent < 100.into(),
+                "sell fee must be in the range [0, 100)"
+            );
 
             // get the curve Component for the math, or create a default_curve with ratio 1:5 and 384 bit precision (which is plenty for the 1e-18 precision of a Decimal)
             // the 384 bit precision is not needed and ignored when built with feature=use_rationals
             let bonding_curve =
                 bonding_curve.unwrap_or_else(|| default_curve::RatioBondingCurve::new(1, 5, 384));
 
-            // use the generated stubs for calling methods on the Component (kind of like a virtual call aka dynamic dispatch, but it happens via the kernel)
-            let curve: crate::bonding_curve::BondingCurve = bonding_curve.clone().into();
+            // use the generated client for calling methods on the Component (kind of like a virtual call aka dynamic dispatch, but it happens via the kernel)
+            let curve: crate::bonding_curve::BondingCurveClient = bonding_curve.clone().into();
 
             // calculate the initial_supply for the initial_reserve
-            let initial_supply = curve.get_initial_supply(initial_reserve.amount());
+            // with a virtual reserve/supply the initial_reserve simply buys at the virtual starting price
+            let initial_supply = if virtual_reserve.is_zero() {
+                curve.get_initial_supply(initial_reserve.amount())
+            } else {
+                curve.get_mint_amount(initial_reserve.amount(), virtual_reserve, virtual_supply)
+            };
 
             // setup auth/badges
             let continuous_auth: BucketOf<AUTH> = ResourceBuilder::new_fungible(DIVISIBILITY_NONE)
@@ -125,25 +303,45 @@ blueprint! {
                     .no_initial_supply()
                     .into();
 
-            // mint the initial supply
-            let mut continuous: BucketOf<CONTINUOUS> = continuous_auth
-                .authorize(|minter| continuous_def.mint(initial_supply, minter).into());
+            // mint the initial supply (which is nothing when starting from zero real reserve)
+            let mut continuous: BucketOf<CONTINUOUS> = if initial_supply.is_zero() {
+                Bucket::new(continuous_def.address()).into()
+            } else {
+                continuous_auth
+                    .authorize(|minter| continuous_def.mint(initial_supply, minter).into())
+            };
+
+            // setup the owner badge, for the fees and the curve migration
+            let owner_badge: BucketOf<OWNER> = ResourceBuilder::new_fungible(DIVISIBILITY_NONE)
+                .metadata("name", "BondingAMM Owner Badge")
+                .initial_supply_fungible(1)
+                .into();
 
             // store and instantiate
             let component = Self {
+                fees: VaultOf::with_bucket(initial_reserve.take(0)),
                 reserve: VaultOf::with_bucket(initial_reserve),
                 continuous: VaultOf::with_bucket(continuous.take(0)),
                 continuous_auth: VaultOf::with_bucket(continuous_auth),
                 bonding_curve,
+                buy_fee_percent,
+                sell_fee_percent,
+                buy_fee_to_reserve,
+                sell_fee_to_reserve,
+                owner_badge: owner_badge.resource_address(),
+                virtual_reserve,
+                virtual_supply,
+                pending_curve: None,
+                migration_notice_epochs: DEFAULT_MIGRATION_NOTICE_EPOCHS,
             }
             .instantiate();
 
-            (component, continuous)
+            (component, continuous, owner_badge)
         }
 
         pub fn buy(
             &mut self,
-            collateral: BucketOf<RESERVE>,
+            mut collateral: BucketOf<RESERVE>,
             minimum_to_receive: Decimal,
         ) -> (BucketOf<CONTINUOUS>, BucketOf<RESERVE>) {
             // mint the right amount of CONTINOUS and return it
@@ -158,7 +356,7 @@ blueprint! {
                 return (self.continuous.take(0), collateral);
             }
 
-            // calculate the amount to mint
+            // calculate the amount to mint (after the buy fee)
             let mint_amount = self.get_buy_quote_amount(collateral.amount());
 
             debug!("will mint CONTINOUS amount: {}", mint_amount);
@@ -171,7 +369,13 @@ blueprint! {
                 return (self.continuous.take(0), collateral);
             }
 
-            // keep the collateral
+            // take the fee
+            let fee_amount = self.get_buy_fee_amount(collateral.amount());
+            if !self.buy_fee_to_reserve {
+                self.fees.put(collateral.take(fee_amount));
+            }
+
+            // keep the collateral (including the fee if it stays in the reserve)
             self.reserve.put(collateral);
 
             // mint for return
@@ -205,8 +409,10 @@ blueprint! {
                 debug!("returning CONTINUOUS amount: {}", continuous.amount());
                 return (self.reserve.take(0), continuous);
             }
-            // calculate the amount to return
-            let return_amount = self.get_sell_quote_amount(continuous.amount());
+            // calculate the amount to return (after the sell fee)
+            let gross_amount = self.get_curve_return_amount(continuous.amount());
+            let fee_amount = self.get_sell_fee_amount(gross_amount);
+            let return_amount = gross_amount - fee_amount;
 
             debug!("will return RESERVE amount: {}", return_amount);
 
@@ -222,58 +428,249 @@ blueprint! {
             self.continuous_auth
                 .authorize(|burner| continuous.burn_with_auth(burner));
 
+            // take the fee
+            if !self.sell_fee_to_reserve {
+                self.fees.put(self.reserve.take(fee_amount));
+            }
+
             // return from reserve vault, and empty CONTINUOUS bucket
             (self.reserve.take(return_amount), self.continuous.take(0))
         }
 
         pub fn get_price(&self) -> Decimal {
-            // use the generated stubs for calling methods on the Component (kind of like a virtual call aka dynamic dispatch, but it happens via the kernel)
-            let curve: crate::bonding_curve::BondingCurve = self.bonding_curve.clone().into();
+            // use the generated client for calling methods on the Component (kind of like a virtual call aka dynamic dispatch, but it happens via the kernel)
+            let curve: crate::bonding_curve::BondingCurveClient = self.bonding_curve.clone().into();
             curve.get_price(
-                self.reserve.amount(),
-                self.continuous.resource_def().total_supply(),
+                self.get_reserve_amount(),
+                self.get_supply_amount(),
             )
         }
 
-        pub fn get_sell_quote(&mut self, continuous_amount: Decimal) -> BucketRefOf<RESERVE> {
-            // This variant returns a BucketRef for proof.  Only possible with sell because
-            // for buying, we can't return "proof" because the amount isn't minted yet
+        pub fn get_buy_quote(&self, collateral_amount: Decimal) -> Quote {
+            // works for any curve since it only needs get_mint_amount and get_price
+            let mint_amount = self.get_buy_quote_amount(collateral_amount);
+            let kept_amount = if self.buy_fee_to_reserve {
+                collateral_amount
+            } else {
+                collateral_amount - self.get_buy_fee_amount(collateral_amount)
+            };
+            Quote::for_buy(
+                collateral_amount,
+                mint_amount,
+                self.get_price(),
+                self.get_price_at(
+                    self.get_reserve_amount() + kept_amount,
+                    self.get_supply_amount() + mint_amount,
+                ),
+            )
+        }
 
-            // use the generated stubs for calling methods on the Component (kind of like a virtual call aka dynamic dispatch, but it happens via the kernel)
-            let curve: crate::bonding_curve::BondingCurve = self.bonding_curve.clone().into();
-            // calculate the amount that would be returned
-            let return_amount = curve.get_return_amount(
+        pub fn get_sell_quote(&self, continuous_amount: Decimal) -> Quote {
+            // works for any curve since it only needs get_return_amount and get_price
+            let gross_amount = self.get_curve_return_amount(continuous_amount);
+            let paid_amount = if self.sell_fee_to_reserve {
+                gross_amount - self.get_sell_fee_amount(gross_amount)
+            } else {
+                gross_amount
+            };
+            Quote::for_sell(
                 continuous_amount,
-                self.reserve.amount(),
-                self.continuous.resource_def().total_supply(),
-            );
-            // return a placeholder for the amount as proof we have it in reserve
-            let bucket = self.reserve.take(return_amount);
-            bucket.present() // return proof of amount, but don't give it away
+                self.get_sell_quote_amount(continuous_amount),
+                self.get_price(),
+                self.get_price_at(
+                    self.get_reserve_amount() - paid_amount,
+                    self.get_supply_amount() - continuous_amount,
+                ),
+            )
         }
 
         pub fn get_buy_quote_amount(&self, collateral_amount: Decimal) -> Decimal {
-            // use the generated stubs for calling methods on the Component (kind of like a virtual call aka dynamic dispatch, but it happens via the kernel)
-            let curve: crate::bonding_curve::BondingCurve = self.bonding_curve.clone().into();
+            // the buy fee is taken from the collateral before it goes to the curve
+            let fee_amount = self.get_buy_fee_amount(collateral_amount);
+            // use the generated client for calling methods on the Component (kind of like a virtual call aka dynamic dispatch, but it happens via the kernel)
+            let curve: crate::bonding_curve::BondingCurveClient = self.bonding_curve.clone().into();
             // calculate amount that would be minted
             curve.get_mint_amount(
-                collateral_amount,
-                self.reserve.amount(),
-                self.continuous.resource_def().total_supply(),
+                collateral_amount - fee_amount,
+                self.get_reserve_amount(),
+                self.get_supply_amount(),
             )
         }
 
         pub fn get_sell_quote_amount(&self, continuous_amount: Decimal) -> Decimal {
-            // interestingly, trying to reuse self.get_sell_quote to get a BucketRef and then look at the amount leads to failure with dangling buckets no matter what I've tried
-            // instead just calculate the amount directly
-            // use the generated stubs for calling methods on the Component (kind of like a virtual call aka dynamic dispatch, but it happens via the kernel)
-            let curve: crate::bonding_curve::BondingCurve = self.bonding_curve.clone().into();
+            let gross_amount = self.get_curve_return_amount(continuous_amount);
+            // the sell fee is taken from the RESERVE the curve would return
+            gross_amount - self.get_sell_fee_amount(gross_amount)
+        }
+
+        pub fn get_virtual_amounts(&self) -> (Decimal, Decimal) {
+            (self.virtual_reserve, self.virtual_supply)
+        }
+
+        pub fn get_fees(&self) -> (Decimal, Decimal) {
+            (self.buy_fee_percent, self.sell_fee_percent)
+        }
+
+        pub fn get_collected_fees_amount(&self) -> Decimal {
+            self.fees.amount()
+        }
+
+        pub fn withdraw_fees(&mut self, owner: BucketRefOf<OWNER>) -> BucketOf<RESERVE> {
+            self.check_owner(owner);
+            self.fees.take_all()
+        }
+
+        // the owner may change the fees, the beneficiary setting (fee_to_reserve) is fixed at construction
+        pub fn set_fees(
+            &mut self,
+            owner: BucketRefOf<OWNER>,
+            buy_fee_percent: Decimal,
+            sell_fee_percent: Decimal,
+        ) {
+            self.check_owner(owner);
+            assert!(
+                !buy_fee_percent.is_negative() && buy_fee_percent < 100.into(),
+                "buy fee must be in the range [0, 100)"
+            );
+            assert!(
+                !sell_fee_percent.is_negative() && sell_fee_percent < 100.into(),
+                "sell fee must be in the range [0, 100)"
+            );
+            self.buy_fee_percent = buy_fee_percent;
+            self.sell_fee_percent = sell_fee_percent;
+        }
+
+        // Governed curve migration: the owner proposes, anyone can execute once the notice period is over.
+        // Holders get the notice period to exit if they don't like the new curve.
+        pub fn propose_curve(&mut self, owner: BucketRefOf<OWNER>, bonding_curve: Address) -> u64 {
+            self.check_owner(owner);
+            let bonding_curve: Component = bonding_curve.into();
+            self.check_curve_solvency(&bonding_curve);
+
+            let effective_epoch = Context::current_epoch() + self.migration_notice_epochs;
+            debug!("curve {} proposed, effective at epoch {}", bonding_curve.address(), effective_epoch);
+            self.pending_curve = Some((bonding_curve, effective_epoch));
+            effective_epoch
+        }
+
+        pub fn cancel_curve_migration(&mut self, owner: BucketRefOf<OWNER>) {
+            self.check_owner(owner);
+            assert!(self.pending_curve.is_some(), "no curve migration pending");
+            self.pending_curve = None;
+        }
+
+        pub fn execute_curve_migration(&mut self) {
+            let (bonding_curve, effective_epoch) =
+                self.pending_curve.clone().expect("no curve migration pending");
+            assert!(
+                Context::current_epoch() >= effective_epoch,
+                "curve migration not possible before epoch {}",
+                effective_epoch
+            );
+            // the reserve/supply may have changed since the proposal
+            self.check_curve_solvency(&bonding_curve);
+
+            self.bonding_curve = bonding_curve;
+            self.pending_curve = None;
+        }
+
+        pub fn get_pending_curve(&self) -> Option<(Address, u64)> {
+            self.pending_curve
+                .as_ref()
+                .map(|(bonding_curve, effective_epoch)| (bonding_curve.address(), *effective_epoch))
+        }
+
+        pub fn get_bonding_curve(&self) -> Address {
+            self.bonding_curve.address()
+        }
+
+        pub fn set_migration_notice_epochs(&mut self, owner: BucketRefOf<OWNER>, epochs: u64) {
+            self.check_owner(owner);
+            // only longer, otherwise the notice period would protect nobody
+            assert!(
+                epochs >= self.migration_notice_epochs,
+                "the migration notice period can only be increased"
+            );
+            self.migration_notice_epochs = epochs;
+        }
+
+        // the new curve has to price the current reserve/supply in a way the reserve can honour:
+        // selling back the whole real supply cannot return more than the real reserve
+        fn check_curve_solvency(&self, bonding_curve: &Component) {
+            let curve: crate::bonding_curve::BondingCurveClient = bonding_curve.clone().into();
+            let real_supply = self.continuous.resource_def().total_supply();
+            let return_amount = curve.get_return_amount(
+                real_supply,
+                self.get_reserve_amount(),
+                self.get_supply_amount(),
+            );
+            assert!(
+                return_amount <= self.reserve.amount(),
+                "new curve is insolvent: selling the supply returns {} but the reserve is {}",
+                return_amount,
+                self.reserve.amount()
+            );
+            // and it has to be able to quote a price at all
+            assert!(
+                !self.get_supply_amount().is_zero()
+                    && curve
+                        .get_price(self.get_reserve_amount(), self.get_supply_amount())
+                        > Decimal::zero(),
+                "new curve has no price for the current reserve/supply"
+            );
+        }
+
+        fn check_owner(&self, owner: BucketRefOf<OWNER>) {
+            assert!(
+                owner.resource_address() == self.owner_badge && !owner.amount().is_zero(),
+                "owner badge required"
+            );
+            owner.drop();
+        }
+
+        fn get_buy_fee_amount(&self, collateral_amount: Decimal) -> Decimal {
+            collateral_amount * self.buy_fee_percent / 100
+        }
+
+        fn get_sell_fee_amount(&self, return_amount: Decimal) -> Decimal {
+            return_amount * self.sell_fee_percent / 100
+        }
+
+        fn get_curve_return_amount(&self, continuous_amount: Decimal) -> Decimal {
+            // use the generated client for calling methods on the Component (kind of like a virtual call aka dynamic dispatch, but it happens via the kernel)
+            let curve: crate::bonding_curve::BondingCurveClient = self.bonding_curve.clone().into();
             // calculate the amount that would be returned
-            curve.get_return_amount(
+            let return_amount = curve.get_return_amount(
                 continuous_amount,
-                self.reserve.amount(),
-                self.continuous.resource_def().total_supply(),
-            )
+                self.get_reserve_amount(),
+                self.get_supply_amount(),
+            );
+            // the virtual reserve can never be paid out, guard against rounding past the real reserve
+            if return_amount > self.reserve.amount() {
+                self.reserve.amount()
+            } else {
+                return_amount
+            }
+        }
+
+        fn get_price_at(&self, reserve_amount: Decimal, supply_amount: Decimal) -> Decimal {
+            if supply_amount.is_zero() {
+                // selling out the whole supply leaves no price
+                return Decimal::zero();
+            }
+            // use the generated client for calling methods on the Component (kind of like a virtual call aka dynamic dispatch, but it happens via the kernel)
+            let curve: crate::bonding_curve::BondingCurveClient = self.bonding_curve.clone().into();
+            curve.get_price(reserve_amount, supply_amount)
+        }
+
+        // the effective (real + virtual) reserve fed to the curve
+        fn get_reserve_amount(&self) -> Decimal {
+            self.reserve.amount() + self.virtual_reserve
+        }
+
+        // the effective (real + virtual) supply fed to the curve
+        fn get_supply_amount(&self) -> Decimal {
+            self.continuous.resource_def().total_supply() + self.virtual_supply
         }
     }
 }
@@ -0,0 +1,282 @@
+//! Deterministic fixed-point math at Decimal precision: `ln`, `exp`, `pow` with any real exponent and `sqrt`
+//!
+//! Internally every value is a `BigInt` scaled by 10^36, twice the 18 decimal places of a Decimal, and every step
+//! truncates back to that scale.  So intermediate values stay around 250 bits no matter the inputs, unlike the
+//! rationals in `number` where the size grows with the denominator of the exponent.
+//!
+//! Error bounds (before the final round half away from zero to 18 decimal places, which adds at most 0.5e-18):
+//! * `sqrt` is exact, so the result is correctly rounded
+//! * `ln` has an absolute error below 1e-32
+//! * `exp` has a relative error below 1e-32
+//! * `pow` and `scaled_pow` have a relative error below (1 + |exponent * ln(base)|) * 1e-32
+//!
+//! Results that don't fit in a Decimal panic, there is no saturation.
+
+use num_bigint::BigInt;
+use num_traits::{Signed, Zero};
+use scrypto::prelude::Decimal;
+
+type Fixed = BigInt;
+
+const DECIMAL_PLACES: u32 = 18;
+const FIXED_PLACES: u32 = 36;
+
+// beyond this exp(x) can't be represented in a Decimal (e^46.6 is the limit) or is far below its precision
+const EXP_LIMIT: i64 = 100;
+
+fn one() -> Fixed {
+    BigInt::from(10u8).pow(FIXED_PLACES)
+}
+
+fn fixed_from_decimal(d: Decimal) -> Fixed {
+    // uses the canonical 'to_vec' API like `number` does
+    let b = BigInt::from_signed_bytes_le(&d.to_vec());
+    b * BigInt::from(10u8).pow(FIXED_PLACES - DECIMAL_PLACES)
+}
+
+fn decimal_from_fixed(f: &Fixed) -> Decimal {
+    // round half away from zero
+    let divisor = BigInt::from(10u8).pow(FIXED_PLACES - DECIMAL_PLACES);
+    let half = &divisor / 2;
+    let b = if f.is_negative() {
+        (f - half) / divisor
+    } else {
+        (f + half) / divisor
+    };
+    decimal_from_bigint(&b)
+}
+
+fn decimal_from_bigint(b: &BigInt) -> Decimal {
+    let mut bytes = b.to_signed_bytes_le();
+    assert!(bytes.len() <= 16, "result out of Decimal range");
+    let pad_byte = if b.is_negative() { 0xFF } else { 0x00 };
+    bytes.resize(16, pad_byte);
+    Decimal::try_from(&bytes[..]).unwrap()
+}
+
+fn mul(a: &Fixed, b: &Fixed) -> Fixed {
+    a * b / one()
+}
+
+fn div(a: &Fixed, b: &Fixed) -> Fixed {
+    assert!(!b.is_zero(), "division by zero");
+    a * one() / b
+}
+
+// 2 * atanh(z) = ln((1 + z) / (1 - z)), converges quickly for small |z|
+fn atanh2(z: &Fixed) -> Fixed {
+    let z2 = mul(z, z);
+    let mut power = z.clone();
+    let mut sum = Fixed::zero();
+    let mut n = 1u32;
+    while !power.is_zero() {
+        sum += &power / n;
+        power = mul(&power, &z2);
+        n += 2;
+    }
+    sum * 2
+}
+
+fn ln2() -> Fixed {
+    // ln(2) = 2 * atanh(1/3)
+    atanh2(&(one() / 3))
+}
+
+fn ln_fixed(x: &Fixed) -> Fixed {
+    assert!(x.is_positive(), "ln is only defined for positive numbers");
+    let one = one();
+    // range reduction: x = m * 2^k with m in [1, 2)
+    let mut k = x.bits() as i64 - one.bits() as i64;
+    let mut m = if k >= 0 {
+        x >> (k as usize)
+    } else {
+        x << ((-k) as usize)
+    };
+    while m >= &one * 2 {
+        m >>= 1;
+        k += 1;
+    }
+    while m < one {
+        m <<= 1;
+        k -= 1;
+    }
+    // ln(m) = 2 * atanh((m - 1) / (m + 1)) with |z| <= 1/3
+    let z = div(&(&m - &one), &(&m + &one));
+    atanh2(&z) + ln2() * k
+}
+
+// None if the result is too large for any Decimal
+fn exp_fixed(x: &Fixed) -> Option<Fixed> {
+    let one = one();
+    if x > &(&one * EXP_LIMIT) {
+        return None;
+    }
+    if x < &(-&one * EXP_LIMIT) {
+        return Some(Fixed::zero());
+    }
+    // range reduction: x = k * ln(2) + r with |r| <= ln(2) / 2
+    let ln2 = ln2();
+    let half = &ln2 / 2;
+    let k: BigInt = if x.is_negative() {
+        (x - &half) / &ln2
+    } else {
+        (x + &half) / &ln2
+    };
+    let r = x - &ln2 * &k;
+    // taylor series for e^r
+    let mut term = one.clone();
+    let mut sum = one.clone();
+    let mut n = 1u32;
+    while !term.is_zero() {
+        term = mul(&term, &r) / n;
+        sum += &term;
+        n += 1;
+    }
+    let k: i64 = k.try_into().unwrap();
+    if k >= 0 {
+        Some(sum << (k as usize))
+    } else {
+        Some(sum >> ((-k) as usize))
+    }
+}
+
+// base^exponent, with 0^exponent = 0 for exponent > 0 and 1 for exponent = 0
+fn pow_fixed(base: &Fixed, exponent: &Fixed) -> Fixed {
+    assert!(!base.is_negative(), "pow is only defined for non-negative bases");
+    if exponent.is_zero() {
+        return one();
+    }
+    if base.is_zero() {
+        assert!(exponent.is_positive(), "pow of zero with a negative exponent");
+        return Fixed::zero();
+    }
+    if base == &one() {
+        return one();
+    }
+    exp_fixed(&mul(exponent, &ln_fixed(base))).expect("result out of Decimal range")
+}
+
+/// natural logarithm, panics for x <= 0
+pub fn ln(x: Decimal) -> Decimal {
+    decimal_from_fixed(&ln_fixed(&fixed_from_decimal(x)))
+}
+
+/// e^x, panics if the result doesn't fit in a Decimal
+pub fn exp(x: Decimal) -> Decimal {
+    decimal_from_fixed(&exp_fixed(&fixed_from_decimal(x)).expect("result out of Decimal range"))
+}
+
+/// base^exponent for any real exponent, panics for a negative base
+pub fn pow(base: Decimal, exponent: Decimal) -> Decimal {
+    decimal_from_fixed(&pow_fixed(
+        &fixed_from_decimal(base),
+        &fixed_from_decimal(exponent),
+    ))
+}
+
+/// scale * (base_n / base_d)^exponent without rounding the intermediate results to Decimal precision
+///
+/// like `number::scaled_power` but with a real exponent, this is the shape of most bonding curve formulas
+pub fn scaled_pow(scale: Decimal, base_n: Decimal, base_d: Decimal, exponent: Decimal) -> Decimal {
+    let base = div(&fixed_from_decimal(base_n), &fixed_from_decimal(base_d));
+    let power = pow_fixed(&base, &fixed_from_decimal(exponent));
+    decimal_from_fixed(&mul(&fixed_from_decimal(scale), &power))
+}
+
+/// square root, correctly rounded, panics for x < 0
+pub fn sqrt(x: Decimal) -> Decimal {
+    assert!(!x.is_negative(), "sqrt is only defined for non-negative numbers");
+    // sqrt(raw * 10^18) is the raw result, one more digit of precision for rounding
+    let raw = BigInt::from_signed_bytes_le(&x.to_vec());
+    let scaled: BigInt = raw * BigInt::from(10u8).pow(DECIMAL_PLACES) * 100u8;
+    let root = scaled.sqrt();
+    let rounded: BigInt = (root + 5u8) / 10;
+    decimal_from_bigint(&rounded)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use scrypto::prelude::*;
+
+    fn d(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_ln() {
+        assert_eq!(ln(d("1")), d("0"));
+        assert_eq!(ln(d("2")), d("0.693147180559945309"));
+        assert_eq!(ln(d("10")), d("2.302585092994045684"));
+        assert_eq!(ln(d("0.5")), d("-0.693147180559945309"));
+        assert_eq!(ln(d("2.718281828459045235")), d("1"));
+        assert_eq!(ln(d("0.000000000000000001")), d("-41.446531673892822312"));
+        assert_eq!(ln(d("100000000000000000000")), d("46.051701859880913680"));
+    }
+
+    #[test]
+    #[should_panic(expected = "ln is only defined for positive numbers")]
+    fn test_ln_zero() {
+        ln(d("0"));
+    }
+
+    #[test]
+    fn test_exp() {
+        assert_eq!(exp(d("0")), d("1"));
+        assert_eq!(exp(d("1")), d("2.718281828459045235"));
+        assert_eq!(exp(d("-1")), d("0.367879441171442322"));
+        assert_eq!(exp(d("0.693147180559945309")), d("1.999999999999999999"));
+        assert_eq!(exp(d("20")), d("485165195.409790277969106831"));
+        assert_eq!(exp(d("-50")), d("0"));
+    }
+
+    #[test]
+    #[should_panic(expected = "result out of Decimal range")]
+    fn test_exp_overflow() {
+        exp(d("47"));
+    }
+
+    #[test]
+    fn test_pow() {
+        assert_eq!(pow(d("2"), d("10")), d("1024"));
+        assert_eq!(pow(d("2"), d("0.5")), d("1.414213562373095049"));
+        assert_eq!(pow(d("10"), d("-0.2")), d("0.630957344480193249"));
+        assert_eq!(pow(d("1.005"), d("0.2")), d("1.000998005979079482"));
+        assert_eq!(pow(d("0"), d("0.3")), d("0"));
+        assert_eq!(pow(d("0"), d("0")), d("1"));
+        assert_eq!(pow(d("123.456"), d("1")), d("123.456"));
+    }
+
+    #[test]
+    fn test_scaled_pow() {
+        // same shape and values as the first buy in default_curve test_1: s * (1 + c/r)^rr - s
+        let minted = scaled_pow(d("300000"), d("60300"), d("60000"), d("0.2")) - d("300000");
+        assert_eq!(minted, d("299.401793723844635041"));
+    }
+
+    #[test]
+    fn test_sqrt() {
+        assert_eq!(sqrt(d("0")), d("0"));
+        assert_eq!(sqrt(d("4")), d("2"));
+        assert_eq!(sqrt(d("2")), d("1.414213562373095049"));
+        assert_eq!(sqrt(d("0.000000000000000001")), d("0.000000001"));
+        assert_eq!(sqrt(d("100000000000000000000")), d("10000000000"));
+    }
+
+    #[test]
+    fn test_identities() {
+        for x in ["0.001", "0.5", "1.5", "3", "1000", "123456789.123456789"] {
+            let x = d(x);
+            // exp(ln(x)) loses at most the rounding of ln(x) scaled by x
+            let roundtrip = exp(ln(x));
+            let tolerance = x * d("0.000000000000000001") + d("0.000000000000000001");
+            assert!(
+                roundtrip - x <= tolerance && x - roundtrip <= tolerance,
+                "exp(ln({})) = {}",
+                x,
+                roundtrip
+            );
+            assert_eq!(pow(x, d("0.5")), sqrt(x));
+        }
+    }
+}
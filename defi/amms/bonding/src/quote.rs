@@ -0,0 +1,113 @@
+use scrypto::prelude::*;
+
+/// The full picture of a trade against a `BondingAMM` before it happens, so a front-end can warn about price impact
+///
+/// Prices are always RESERVE per CONTINUOUS, and all amounts already have the AMM fees taken out
+#[derive(Debug, Clone, PartialEq, sbor::TypeId, sbor::Encode, sbor::Decode, sbor::Describe)]
+pub struct Quote {
+    /// the amount sent in (RESERVE for a buy, CONTINUOUS for a sell)
+    pub input_amount: Decimal,
+    /// the amount that would be received (CONTINUOUS for a buy, RESERVE for a sell)
+    pub output_amount: Decimal,
+    /// RESERVE paid or received per CONTINUOUS over the whole trade, including fees
+    pub average_price: Decimal,
+    /// the curve price before the trade
+    pub spot_price_before: Decimal,
+    /// the curve price after the trade
+    pub spot_price_after: Decimal,
+    /// how much worse than `spot_price_before` the `average_price` is, in percent (never negative)
+    pub price_impact_percent: Decimal,
+}
+
+impl Quote {
+    pub fn for_buy(
+        collateral_amount: Decimal,
+        mint_amount: Decimal,
+        spot_price_before: Decimal,
+        spot_price_after: Decimal,
+    ) -> Self {
+        let average_price = if mint_amount.is_zero() {
+            Decimal::zero()
+        } else {
+            collateral_amount / mint_amount
+        };
+        Self::new(
+            collateral_amount,
+            mint_amount,
+            average_price,
+            spot_price_before,
+            spot_price_after,
+        )
+    }
+
+    pub fn for_sell(
+        continuous_amount: Decimal,
+        return_amount: Decimal,
+        spot_price_before: Decimal,
+        spot_price_after: Decimal,
+    ) -> Self {
+        let average_price = if continuous_amount.is_zero() {
+            Decimal::zero()
+        } else {
+            return_amount / continuous_amount
+        };
+        Self::new(
+            continuous_amount,
+            return_amount,
+            average_price,
+            spot_price_before,
+            spot_price_after,
+        )
+    }
+
+    fn new(
+        input_amount: Decimal,
+        output_amount: Decimal,
+        average_price: Decimal,
+        spot_price_before: Decimal,
+        spot_price_after: Decimal,
+    ) -> Self {
+        // buying pays above spot and selling receives below spot, either way the impact is the distance from spot
+        let price_impact_percent = if output_amount.is_zero() || spot_price_before.is_zero() {
+            Decimal::zero()
+        } else if average_price > spot_price_before {
+            (average_price - spot_price_before) / spot_price_before * 100
+        } else {
+            (spot_price_before - average_price) / spot_price_before * 100
+        };
+        Self {
+            input_amount,
+            output_amount,
+            average_price,
+            spot_price_before,
+            spot_price_after,
+            price_impact_percent,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_buy_impact() {
+        let quote = Quote::for_buy(110.into(), 100.into(), 1.into(), 2.into());
+        assert_eq!(quote.average_price, Decimal::from_str("1.1").unwrap());
+        assert_eq!(quote.price_impact_percent, 10.into());
+    }
+
+    #[test]
+    fn test_sell_impact() {
+        let quote = Quote::for_sell(100.into(), 75.into(), 1.into(), Decimal::from_str("0.5").unwrap());
+        assert_eq!(quote.average_price, Decimal::from_str("0.75").unwrap());
+        assert_eq!(quote.price_impact_percent, 25.into());
+    }
+
+    #[test]
+    fn test_nothing_out() {
+        let quote = Quote::for_buy(1.into(), 0.into(), 1.into(), 1.into());
+        assert_eq!(quote.average_price, 0.into());
+        assert_eq!(quote.price_impact_percent, 0.into());
+    }
+}
@@ -0,0 +1,169 @@
+use scrypto::prelude::*;
+
+use crate::math::scaled_pow;
+
+fn get_initial_supply(collateral_amount: Decimal, reserve_ratio: Decimal) -> Decimal {
+    assert!(!collateral_amount.is_negative());
+
+    // collateral_amount / reserve_ratio, so the initial price is 1
+    collateral_amount / reserve_ratio
+}
+
+fn get_mint_amount(
+    collateral_amount: Decimal,
+    reserve_amount: Decimal,
+    supply_amount: Decimal,
+    reserve_ratio: Decimal,
+) -> Decimal {
+    assert!(!collateral_amount.is_negative());
+    assert!(!reserve_amount.is_negative());
+    assert!(!supply_amount.is_negative());
+
+    if collateral_amount.is_zero() {
+        return Decimal::zero();
+    }
+
+    // PurchaseReturn = ContinuousTokenSupply * ((1 + ReserveTokensReceived / ReserveTokenBalance) ^ (ReserveRatio) - 1)
+    // = s * ((c + r) / r) ^ rr - s
+    let result = scaled_pow(
+        supply_amount,
+        collateral_amount + reserve_amount,
+        reserve_amount,
+        reserve_ratio,
+    ) - supply_amount;
+
+    assert!(!result.is_negative(), "Calculated negative mint amount");
+
+    result
+}
+
+fn get_return_amount(
+    continuous_amount: Decimal,
+    reserve_amount: Decimal,
+    supply_amount: Decimal,
+    reserve_ratio: Decimal,
+) -> Decimal {
+    assert!(!continuous_amount.is_negative());
+    assert!(!reserve_amount.is_negative());
+    assert!(!supply_amount.is_negative());
+
+    if continuous_amount.is_zero() {
+        return Decimal::zero();
+    }
+
+    // SaleReturn = ReserveTokenBalance * (1 - (1 - ContinuousTokensReceived / ContinuousTokenSupply) ^ (1 / (ReserveRatio)))
+    // = r - r * ((s - c) / s) ^ (1 / rr)
+    let result = reserve_amount
+        - scaled_pow(
+            reserve_amount,
+            supply_amount - continuous_amount,
+            supply_amount,
+            Decimal::from(1) / reserve_ratio,
+        );
+
+    assert!(!result.is_negative(), "Calculated negative return amount");
+
+    result
+}
+
+blueprint! {
+    struct WeightedBondingCurve {
+        reserve_ratio: Decimal,
+    }
+
+    impl WeightedBondingCurve {
+        // like RatioBondingCurve, but the reserve ratio (aka "curve weight") can be any Decimal in (0, 1]
+        // the math is fixed-point so there is no precision parameter and no blow-up for awkward ratios
+        pub fn new(reserve_ratio: Decimal) -> Component {
+            debug!("WeightedBondingCurve::new called with {}", reserve_ratio);
+            assert!(
+                reserve_ratio > Decimal::zero() && reserve_ratio <= 1.into(),
+                "reserve ratio must be in the range (0, 1]"
+            );
+            Self { reserve_ratio }.instantiate()
+        }
+
+        // can't do trait impl's within the blueprint.  Would be ncie to get those compile time checks...
+        // impl BondingCurve for blueprint::WeightedBondingCurve {
+
+        pub fn get_initial_supply(&self, collateral_amount: Decimal) -> Decimal {
+            get_initial_supply(collateral_amount, self.reserve_ratio)
+        }
+
+        pub fn get_mint_amount(
+            &self,
+            collateral_amount: Decimal,
+            reserve_amount: Decimal,
+            supply_amount: Decimal,
+        ) -> Decimal {
+            get_mint_amount(
+                collateral_amount,
+                reserve_amount,
+                supply_amount,
+                self.reserve_ratio,
+            )
+        }
+
+        pub fn get_return_amount(
+            &self,
+            continuous_amount: Decimal,
+            reserve_amount: Decimal,
+            supply_amount: Decimal,
+        ) -> Decimal {
+            get_return_amount(
+                continuous_amount,
+                reserve_amount,
+                supply_amount,
+                self.reserve_ratio,
+            )
+        }
+
+        pub fn get_price(&self, reserve_amount: Decimal, supply_amount: Decimal) -> Decimal {
+            //  Continuous Token Price = Reserve Token Balance / (Continuous Token Supply x Reserve Ratio)
+            reserve_amount / (supply_amount * self.reserve_ratio)
+        }
+
+        // }
+    }
+}
+
+// -------- Testing
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_1_buy() {
+        // same as default_curve test_1 with reserve ratio 1/5
+        let reserve_ratio = Decimal::from_str("0.2").unwrap();
+        let to_mint = get_mint_amount(300.into(), 60000.into(), 300000.into(), reserve_ratio);
+        let expected: Decimal = Decimal(299401793723844635041); // 299.401793723844635041
+        assert_eq!(to_mint, expected);
+    }
+
+    #[test]
+    fn test_2_buy_sell() {
+        let reserve_ratio = Decimal::from_str("0.2").unwrap();
+        let supply_amount = Decimal(300000000000000000000000i128 + 299401793723844635041i128);
+        let to_return = get_return_amount(
+            Decimal(299401793723844635041),
+            60300.into(),
+            supply_amount,
+            reserve_ratio,
+        );
+        assert_eq!(to_return, 300.into());
+    }
+
+    #[test]
+    fn test_3_arbitrary_weight() {
+        // a weight that would need a 1000th root with rationals
+        let reserve_ratio = Decimal::from_str("0.333").unwrap();
+        let to_mint = get_mint_amount(1000.into(), 100000.into(), 300300.into(), reserve_ratio);
+        assert!(to_mint > 0.into());
+        // selling back what was minted returns what was paid, within rounding
+        let to_return = get_return_amount(to_mint, 101000.into(), Decimal::from(300300) + to_mint, reserve_ratio);
+        let difference = Decimal::from(1000) - to_return;
+        assert!(!difference.is_negative() && difference < Decimal::from_str("0.000000000001").unwrap());
+    }
+}
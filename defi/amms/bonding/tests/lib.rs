@@ -1,5 +1,6 @@
 use radix_engine::ledger::*;
 use scrypto::prelude::*;
+use scrypto_bonding::Quote;
 use scrypto_unit::*;
 
 fn setup_fixture<'a, L: SubstateStore>(env: &mut TestEnv<'a, L>) -> (User, User, ResourceDef) {
@@ -83,7 +84,7 @@ fn test_1() {
     // since it is not the returned Component but just the one that is created first.
 
     // instead do this
-    let ret: (Component, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
+    let ret: (Component, Bucket, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
     let amm = ret.0;
 
     // now switch to an investor
@@ -200,7 +201,7 @@ fn test_2_basic_curve() {
     // since it is not the returned Component but just the one that is created first.
 
     // do this instead
-    let ret: (Component, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
+    let ret: (Component, Bucket, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
     let amm = ret.0;
 
     // now switch to an investor
@@ -295,7 +296,7 @@ fn test_3_quotes() {
     let _control_addr = receipt.resource_def(0).unwrap(); // this should be the CONTROL
     let _continuous_addr = receipt.resource_def(1).unwrap(); // this should be the CONTINUOUS
 
-    let ret: (Component, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
+    let ret: (Component, Bucket, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
     let amm = ret.0;
 
     // now switch to an investor
@@ -320,23 +321,443 @@ fn test_3_quotes() {
     let quote: Decimal = return_of_call_method(&mut receipt, "get_sell_quote_amount");
     assert_eq!(quote, 300.into());
 
-    /* this test no longer works in v0.3.0 because we can't return a BucketRef.
-     * The code is still a good example of something another blueprint might use
-     * but we just can't easily test it.  Leave here to update later
-
-    // now try to get a seel quote (BucketRef) ...but can't check it easily outside of wasm
-    let receipt = env.call_method(&amm.address(), "get_sell_quote", vec![
-        format!("300"),
-    ]);
+    // the structured quotes agree with the amounts, and the flat curve has no price impact
+    let mut receipt = env.call_method(&amm.address(), "get_buy_quote", vec![format!("300")]);
+    println!("buy_quote: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+    let quote: Quote = return_of_call_method(&mut receipt, "get_buy_quote");
+    assert_eq!(quote.output_amount, 300.into());
+    assert_eq!(quote.average_price, 1.into());
+    assert_eq!(quote.spot_price_before, 1.into());
+    assert_eq!(quote.spot_price_after, 1.into());
+    assert_eq!(quote.price_impact_percent, 0.into());
+
+    let mut receipt = env.call_method(&amm.address(), "get_sell_quote", vec![format!("300")]);
     println!("sell_quote: receipt: {:?}", receipt);
     assert!(receipt.result.is_ok());
+    let quote: Quote = return_of_call_method(&mut receipt, "get_sell_quote");
+    assert_eq!(quote.output_amount, 300.into());
+    assert_eq!(quote.price_impact_percent, 0.into());
 
-    // cant introspect the BucketRef, but at least the tx succeeded
+    // verify we didn't somehow actually get any RESERVE back from quoting
+    let expected_reserve_in_account: Decimal = 1_000_000.into();
+    let reserve_in_account = env.get_amount_for_rd(user.account, reserve_def.address());
+    assert_eq!(reserve_in_account, expected_reserve_in_account);
+}
 
-    */
+#[test]
+fn test_4_fees() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut env = TestEnv::new(&mut ledger);
+    let (owner, investor, reserve_def) = setup_fixture(&mut env);
 
-    // verify we didn't somehow actually get any RESERVE back from the BucketRef stuff
-    let expected_reserve_in_account: Decimal = 1_000_000.into();
+    const BLUEPRINT: &str = "BondingAMM";
+
+    // switch to the owner to instantiate a new BondingAMM
+    env.acting_as("owner");
+
+    // 1% buy fee and 2% sell fee, both go to the beneficiary vault
+    let mut receipt = env.call_function(
+        BLUEPRINT,
+        "new_default_with_fees",
+        vec![
+            format!("60000,{}", reserve_def.address()),
+            "Continuous".to_owned(),
+            "XC".to_owned(),
+            format!("1"),
+            format!("2"),
+            format!("false"),
+            format!("false"),
+        ],
+    );
+    println!("new_default_with_fees: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+
+    // save off addresses
+    // this is brittle checking the defs based on order...
+    let _control_addr = receipt.resource_def(0).unwrap(); // this should be the CONTROL
+    let continuous_addr = receipt.resource_def(1).unwrap(); // this should be the CONTINUOUS
+    let owner_addr = receipt.resource_def(2).unwrap(); // this should be the OWNER
+
+    let ret: (Component, Bucket, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
+    let amm = ret.0;
+
+    // now switch to an investor
+    env.acting_as("investor");
+    let user = investor;
+
+    // the buy quote should already have the fee taken out: 300 - 1% = 297 goes to the curve
+    let mut receipt = env.call_method(&amm.address(), "get_buy_quote_amount", vec![format!("300")]);
+    assert!(receipt.result.is_ok());
+    let buy_quote: Decimal = return_of_call_method(&mut receipt, "get_buy_quote_amount");
+
+    let receipt = env.call_method(
+        &amm.address(),
+        "buy",
+        vec![format!("300,{}", reserve_def.address()), format!("0")],
+    );
+    println!("buy: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+
+    // the quote was exact
+    let continuous_in_account = env.get_amount_for_rd(user.account, continuous_addr);
+    assert_eq!(continuous_in_account, buy_quote);
+    // and less than the fee-free amount from test_1
+    assert!(continuous_in_account < Decimal::from_str("299.401793723844635041").unwrap());
+
+    // 1% of 300 was collected
+    let mut receipt = env.call_method(&amm.address(), "get_collected_fees_amount", vec![]);
+    assert!(receipt.result.is_ok());
+    let collected: Decimal = return_of_call_method(&mut receipt, "get_collected_fees_amount");
+    assert_eq!(collected, 3.into());
+
+    // sell everything back, the quote should be exact here too
+    let mut receipt = env.call_method(
+        &amm.address(),
+        "get_sell_quote_amount",
+        vec![format!("{}", continuous_in_account)],
+    );
+    assert!(receipt.result.is_ok());
+    let sell_quote: Decimal = return_of_call_method(&mut receipt, "get_sell_quote_amount");
+
+    let receipt = env.call_method(
+        &amm.address(),
+        "sell",
+        vec![
+            format!("{},{}", continuous_in_account, continuous_addr),
+            format!("0"),
+        ],
+    );
+    println!("sell: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+
+    let expected_reserve_in_account: Decimal = Decimal::from(1_000_000) - 300 + sell_quote;
     let reserve_in_account = env.get_amount_for_rd(user.account, reserve_def.address());
     assert_eq!(reserve_in_account, expected_reserve_in_account);
+
+    // the sell fee (2% of what the curve returned) was added to the collected fees
+    let mut receipt = env.call_method(&amm.address(), "get_collected_fees_amount", vec![]);
+    assert!(receipt.result.is_ok());
+    let collected: Decimal = return_of_call_method(&mut receipt, "get_collected_fees_amount");
+    assert!(collected > 3.into());
+    assert!(collected - 3 < sell_quote * 3 / 98); // about 2/98ths of the sell quote
+
+    // investor can't withdraw the fees
+    let receipt = env.call_method(
+        &amm.address(),
+        "withdraw_fees",
+        vec![format!("1,{}", reserve_def.address())],
+    );
+    assert!(!receipt.result.is_ok());
+
+    // owner can
+    env.acting_as("owner");
+    let user = owner;
+    let reserve_before = env.get_amount_for_rd(user.account, reserve_def.address());
+    let receipt = env.call_method(
+        &amm.address(),
+        "withdraw_fees",
+        vec![format!("1,{}", owner_addr)],
+    );
+    println!("withdraw_fees: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+    let reserve_after = env.get_amount_for_rd(user.account, reserve_def.address());
+    assert_eq!(reserve_after - reserve_before, collected);
+}
+
+#[test]
+fn test_5_virtual_reserve() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut env = TestEnv::new(&mut ledger);
+    let (owner, investor, reserve_def) = setup_fixture(&mut env);
+
+    const BLUEPRINT: &str = "BondingAMM";
+
+    // switch to the owner to instantiate a new BondingAMM
+    env.acting_as("owner");
+    let user = owner;
+
+    // start from zero real reserve, with the same virtual reserve/supply as the real ones in test_1
+    let mut receipt = env.call_function(
+        BLUEPRINT,
+        "new_default_with_virtual",
+        vec![
+            format!("0,{}", reserve_def.address()),
+            "Continuous".to_owned(),
+            "XC".to_owned(),
+            format!("60000"),
+            format!("300000"),
+        ],
+    );
+    println!("new_default_with_virtual: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+
+    // save off addresses
+    // this is brittle checking the defs based on order...
+    let _control_addr = receipt.resource_def(0).unwrap(); // this should be the CONTROL
+    let continuous_addr = receipt.resource_def(1).unwrap(); // this should be the CONTINUOUS
+
+    // no reserve was seeded and nothing was self-minted
+    let reserve_in_account = env.get_amount_for_rd(user.account, reserve_def.address());
+    assert_eq!(reserve_in_account, 1_000_000.into());
+    let continuous_in_account = env.get_amount_for_rd(user.account, continuous_addr);
+    assert_eq!(continuous_in_account, 0.into());
+
+    let ret: (Component, Bucket, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
+    let amm = ret.0;
+
+    // the starting price is the price of the virtual curve: 60000 / (300000 * 1/5)
+    let mut receipt = env.call_method(&amm.address(), "get_price", vec![]);
+    assert!(receipt.result.is_ok());
+    let price: Decimal = return_of_call_method(&mut receipt, "get_price");
+    assert_eq!(price, 1.into());
+
+    // now switch to an investor
+    env.acting_as("investor");
+    let user = investor;
+
+    // buying gives exactly the same as test_1 since the curve sees the same effective amounts
+    let receipt = env.call_method(
+        &amm.address(),
+        "buy",
+        vec![format!("300,{}", reserve_def.address()), format!("0")],
+    );
+    println!("buy: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+    let expected_continuous_in_account = Decimal::from_str("299.401793723844635041").unwrap();
+    let continuous_in_account = env.get_amount_for_rd(user.account, continuous_addr);
+    assert_eq!(continuous_in_account, expected_continuous_in_account);
+
+    // and selling it back returns the whole real reserve
+    let receipt = env.call_method(
+        &amm.address(),
+        "sell",
+        vec![
+            format!("299.401793723844635041,{}", continuous_addr),
+            format!("0"),
+        ],
+    );
+    println!("sell: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+    let reserve_in_account = env.get_amount_for_rd(user.account, reserve_def.address());
+    assert_eq!(reserve_in_account, 1_000_000.into());
+}
+
+#[test]
+fn test_6_price_impact() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut env = TestEnv::new(&mut ledger);
+    let (_owner, _investor, reserve_def) = setup_fixture(&mut env);
+
+    const BLUEPRINT: &str = "BondingAMM";
+
+    // switch to the owner to instantiate a new BondingAMM
+    env.acting_as("owner");
+
+    let mut receipt = env.call_function(
+        BLUEPRINT,
+        "new_default",
+        vec![
+            format!("60000,{}", reserve_def.address()),
+            "Continuous".to_owned(),
+            "XC".to_owned(),
+        ],
+    );
+    assert!(receipt.result.is_ok());
+    let ret: (Component, Bucket, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
+    let amm = ret.0;
+
+    env.acting_as("investor");
+
+    // same trade as test_1, the price rises along the curve
+    let mut receipt = env.call_method(&amm.address(), "get_buy_quote", vec![format!("300")]);
+    println!("buy_quote: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+    let buy_quote: Quote = return_of_call_method(&mut receipt, "get_buy_quote");
+    assert_eq!(
+        buy_quote.output_amount,
+        Decimal::from_str("299.401793723844635041").unwrap()
+    );
+    assert_eq!(buy_quote.spot_price_before, 1.into());
+    assert!(buy_quote.average_price > buy_quote.spot_price_before);
+    assert!(buy_quote.spot_price_after > buy_quote.average_price);
+    assert!(buy_quote.price_impact_percent > 0.into());
+
+    // a bigger trade has a bigger impact
+    let mut receipt = env.call_method(&amm.address(), "get_buy_quote", vec![format!("30000")]);
+    assert!(receipt.result.is_ok());
+    let big_buy_quote: Quote = return_of_call_method(&mut receipt, "get_buy_quote");
+    assert!(big_buy_quote.price_impact_percent > buy_quote.price_impact_percent);
+
+    // selling moves the price down
+    let mut receipt = env.call_method(&amm.address(), "get_sell_quote", vec![format!("300")]);
+    println!("sell_quote: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+    let sell_quote: Quote = return_of_call_method(&mut receipt, "get_sell_quote");
+    assert!(sell_quote.average_price < sell_quote.spot_price_before);
+    assert!(sell_quote.spot_price_after < sell_quote.average_price);
+    assert!(sell_quote.price_impact_percent > 0.into());
+}
+
+#[test]
+fn test_7_curve_migration() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut env = TestEnv::new(&mut ledger);
+    let (_owner, _investor, reserve_def) = setup_fixture(&mut env);
+
+    const BLUEPRINT: &str = "BondingAMM";
+    const CURVE_BLUEPRINT: &str = "BasicBondingCurve";
+    const RATIO_CURVE_BLUEPRINT: &str = "RatioBondingCurve";
+
+    // switch to the owner to instantiate a new BondingAMM, every constructor returns the owner badge
+    env.acting_as("owner");
+
+    let mut receipt = env.call_function(
+        BLUEPRINT,
+        "new_default",
+        vec![
+            format!("60000,{}", reserve_def.address()),
+            "Continuous".to_owned(),
+            "XC".to_owned(),
+        ],
+    );
+    assert!(receipt.result.is_ok());
+    let owner_addr = receipt.resource_def(2).unwrap(); // this should be the OWNER
+    let ret: (Component, Bucket, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
+    let amm = ret.0;
+
+    // the flat curve would pay out 300000 for the supply, but only 60000 is in reserve
+    let mut receipt = env.call_function(CURVE_BLUEPRINT, "new", vec![]);
+    let basic_curve: Component = return_of_call_function(&mut receipt, CURVE_BLUEPRINT);
+    let receipt = env.call_method(
+        &amm.address(),
+        "propose_curve",
+        vec![format!("1,{}", owner_addr), format!("{}", basic_curve.address())],
+    );
+    println!("propose_curve (insolvent): receipt: {:?}", receipt);
+    assert!(!receipt.result.is_ok());
+
+    // a steeper ratio curve returns exactly the reserve for the whole supply, so it's solvent
+    let mut receipt = env.call_function(
+        RATIO_CURVE_BLUEPRINT,
+        "new",
+        vec![format!("1"), format!("4"), format!("384")],
+    );
+    let ratio_curve: Component = return_of_call_function(&mut receipt, RATIO_CURVE_BLUEPRINT);
+
+    // only the owner can propose
+    env.acting_as("investor");
+    let receipt = env.call_method(
+        &amm.address(),
+        "propose_curve",
+        vec![
+            format!("1,{}", reserve_def.address()),
+            format!("{}", ratio_curve.address()),
+        ],
+    );
+    assert!(!receipt.result.is_ok());
+
+    env.acting_as("owner");
+    let mut receipt = env.call_method(
+        &amm.address(),
+        "propose_curve",
+        vec![format!("1,{}", owner_addr), format!("{}", ratio_curve.address())],
+    );
+    println!("propose_curve: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+    let effective_epoch: u64 = return_of_call_method(&mut receipt, "propose_curve");
+    assert_eq!(effective_epoch, 100);
+
+    let mut receipt = env.call_method(&amm.address(), "get_pending_curve", vec![]);
+    assert!(receipt.result.is_ok());
+    let pending: Option<(Address, u64)> = return_of_call_method(&mut receipt, "get_pending_curve");
+    assert_eq!(pending, Some((ratio_curve.address(), 100)));
+
+    // nobody can switch before the notice period is over
+    env.acting_as("investor");
+    let receipt = env.call_method(&amm.address(), "execute_curve_migration", vec![]);
+    println!("execute_curve_migration (too early): receipt: {:?}", receipt);
+    assert!(!receipt.result.is_ok());
+
+    // the notice period can't be shortened
+    env.acting_as("owner");
+    let receipt = env.call_method(
+        &amm.address(),
+        "set_migration_notice_epochs",
+        vec![format!("1,{}", owner_addr), format!("10")],
+    );
+    assert!(!receipt.result.is_ok());
+
+    // the owner can change their mind
+    let receipt = env.call_method(
+        &amm.address(),
+        "cancel_curve_migration",
+        vec![format!("1,{}", owner_addr)],
+    );
+    assert!(receipt.result.is_ok());
+    let mut receipt = env.call_method(&amm.address(), "get_pending_curve", vec![]);
+    let pending: Option<(Address, u64)> = return_of_call_method(&mut receipt, "get_pending_curve");
+    assert_eq!(pending, None);
+}
+
+#[test]
+fn test_8_curve_migration_after_notice() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let (amm, ratio_curve) = {
+        let mut env = TestEnv::new(&mut ledger);
+        let (_owner, _investor, reserve_def) = setup_fixture(&mut env);
+
+        const BLUEPRINT: &str = "BondingAMM";
+        const RATIO_CURVE_BLUEPRINT: &str = "RatioBondingCurve";
+
+        env.acting_as("owner");
+        let mut receipt = env.call_function(
+            BLUEPRINT,
+            "new_default",
+            vec![
+                format!("60000,{}", reserve_def.address()),
+                "Continuous".to_owned(),
+                "XC".to_owned(),
+            ],
+        );
+        assert!(receipt.result.is_ok());
+        let owner_addr = receipt.resource_def(2).unwrap(); // this should be the OWNER
+        let ret: (Component, Bucket, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
+        let amm = ret.0;
+
+        let mut receipt = env.call_function(
+            RATIO_CURVE_BLUEPRINT,
+            "new",
+            vec![format!("1"), format!("4"), format!("384")],
+        );
+        let ratio_curve: Component = return_of_call_function(&mut receipt, RATIO_CURVE_BLUEPRINT);
+
+        let mut receipt = env.call_method(
+            &amm.address(),
+            "propose_curve",
+            vec![format!("1,{}", owner_addr), format!("{}", ratio_curve.address())],
+        );
+        assert!(receipt.result.is_ok());
+        let effective_epoch: u64 = return_of_call_method(&mut receipt, "propose_curve");
+        assert_eq!(effective_epoch, 100);
+
+        (amm, ratio_curve)
+    };
+
+    // the notice period is over
+    ledger.set_epoch(100);
+    let mut env = TestEnv::new(&mut ledger);
+    env.create_user("anyone");
+    env.acting_as("anyone");
+
+    let receipt = env.call_method(&amm.address(), "execute_curve_migration", vec![]);
+    println!("execute_curve_migration: receipt: {:?}", receipt);
+    assert!(receipt.result.is_ok());
+
+    let mut receipt = env.call_method(&amm.address(), "get_bonding_curve", vec![]);
+    let bonding_curve: Address = return_of_call_method(&mut receipt, "get_bonding_curve");
+    assert_eq!(bonding_curve, ratio_curve.address());
+    let mut receipt = env.call_method(&amm.address(), "get_pending_curve", vec![]);
+    let pending: Option<(Address, u64)> = return_of_call_method(&mut receipt, "get_pending_curve");
+    assert_eq!(pending, None);
 }
@@ -18,12 +18,13 @@ CP_OP=$(resim call-function $package CrowdsourcingCampaign new 10000 1)
 export component=$(echo "$CP_OP" | sed -nr "s/└─ Component: ([[:alnum:]_]+)/\1/p")
 export fundraiser_badge=$(echo "$CP_OP" | sed -nr "s/.*Resource: ([[:alnum:]_]+)/\1/p" | sed '1!d')
 export patron_mint_badge=$(echo "$CP_OP" | sed -nr "s/.*Resource: ([[:alnum:]_]+)/\1/p" | sed '1!d')
+export pledge_badge=$(echo "$CP_OP" | sed -nr "s/.*Resource: ([[:alnum:]_]+)/\1/p" | sed '3!d')
 
 resim set-default-account $acct2 $privkey2
 resim call-method $component pledge 5000,$xrd
 
 resim set-default-account $acct2 $privkey2
-resim call-method $component recall_pledge 1,$pledge_badge
+resim call-method $component recall_pledge "#0000000000000000,$pledge_badge"
 
 resim set-default-account $acct1 $privkey1
 resim call-method $component withdraw 1,$fundraiser_badge
\ No newline at end of file
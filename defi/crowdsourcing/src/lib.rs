@@ -1,19 +1,113 @@
+use sbor::*;
 use scrypto::prelude::*;
 
+mod registry;
+
+#[derive(TypeId, Encode, Decode, Describe, Debug, Clone, PartialEq)]
+pub enum CampaignStatus {
+    // Patrons can still pledge.
+    Active,
+    // Ended with its goal met, and no milestone was rejected.
+    Succeeded,
+    Failed,
+}
+
+#[derive(TypeId, Encode, Decode, Describe, Debug, Clone, PartialEq)]
+pub enum MilestoneStatus {
+    // Waiting for the previous milestones.
+    Pending,
+    // Patrons can vote until this epoch.
+    Voting(u64),
+    // Its portion of the collected XRD was released to the fundraiser.
+    Approved,
+    // Patrons can recall what is left of their pledge.
+    Rejected,
+}
+
+#[derive(TypeId, Encode, Decode, Describe, Debug, Clone, PartialEq)]
+pub struct Milestone {
+    description: String,
+    // Percentage of the XRD collected at the end of the campaign that the milestone unlocks.
+    percent: Decimal,
+    status: MilestoneStatus,
+    // Pledged XRD of the patrons who voted for and against.
+    votes_for: Decimal,
+    votes_against: Decimal,
+    // Patron badges that already voted.
+    voters: Vec<NonFungibleId>,
+}
+
+#[derive(TypeId, Encode, Decode, Describe, Debug, Clone, PartialEq)]
+pub struct RewardTier {
+    perk: String,
+    // Minimum pledge in XRD to get the perk.
+    min_pledge: Decimal,
+    // Number of patrons that can get the perk.
+    quantity: u64,
+    // Number of patrons that got the perk.
+    claimed: u64,
+    // XRD the campaign has to collect before the tier is offered, None for tiers offered from the start.
+    stretch_goal: Option<Decimal>,
+}
+
+// Patron badge, one per pledge.
+#[derive(NonFungibleData)]
+pub struct Patron {
+    pledge: Decimal,
+    // Reward tier of the pledge and its perk.
+    tier: Option<u64>,
+    perk: Option<String>,
+}
+
+fn divisibility(resource: ResourceAddress) -> u8 {
+    match borrow_resource_manager!(resource).resource_type() {
+        ResourceType::Fungible { divisibility } => divisibility,
+        ResourceType::NonFungible => panic!("pledges must be paid with a fungible resource."),
+    }
+}
+
+// Amounts are rounded down to what the payment resource can hold, `take` fails on a finer amount.
+fn round_down(amount: Decimal, divisibility: u8) -> Decimal {
+    let unit = 10i128.pow(18 - divisibility as u32);
+    Decimal(amount.0 / unit * unit)
+}
+
 blueprint! {
     struct CrowdsourcingCampaign {
-        // Collected XRD for the crowdsourcing campaign.
+        // Collected XRD for the crowdsourcing campaign, or the payment resource of the campaign.
         collected_xrd: Vault,
         // Definition of fundraisers badge.
         fundraiser_badge_address: ResourceAddress,
         // Used to mint mutable badges.
         patron_mint_badge: Vault,
+        // Patron badges, one NFT per pledge.
+        patron_resource: ResourceAddress,
+        // Id of the next patron badge.
+        patron_count: u64,
         // The amount that has to be collected for the contract to be fullfilled.
         goal: Decimal,
         // Last epoch of the campaign.
         last_epoch: u64,
         // Address book of all patron badges, and the amount they pledged.
-        patron_entries: HashMap<ResourceAddress, Decimal>,
+        patron_entries: HashMap<NonFungibleId, Decimal>,
+        // Reward tiers patrons can pledge for, stretch goals included.
+        reward_tiers: Vec<RewardTier>,
+        // Most XRD collected at once, stretch goals stay unlocked when patrons recall their pledge.
+        highest_collected: Decimal,
+        // Milestones voted on in order by the patrons, empty when everything is withdrawn at once.
+        milestones: Vec<Milestone>,
+        // Epochs patrons have to vote on a milestone.
+        vote_epochs: u64,
+        // Epochs the fundraiser has to open the vote on the next milestone, after the campaign or the previous vote.
+        milestone_epochs: u64,
+        // After this epoch anyone can open the vote on the next milestone.
+        milestone_deadline: u64,
+        // XRD of the approved milestones, until the fundraiser withdraws it.
+        released_xrd: Vault,
+        // Percentage of the withdrawn XRD kept for the platform that created the campaign.
+        platform_fee: Decimal,
+        // Fees until the platform collects them.
+        platform_fees: Vault,
     }
 
     impl CrowdsourcingCampaign {
@@ -22,6 +116,90 @@ blueprint! {
         epoch duration for the campaign to be finished.
         */
         pub fn new(goal: Decimal, campaign_duration_epochs: u64) -> (ComponentAddress, Bucket) {
+            Self::new_with_milestones(goal, campaign_duration_epochs, Vec::new(), 0, 0)
+        }
+
+        /*
+        Same as new, with the collected XRD released by milestones of (description, percentage) instead of all at once.
+        The percentages must add up to 100. Patrons vote on each milestone for vote_epochs, weighted by their pledge.
+        The fundraiser opens each vote within milestone_epochs of the end of the campaign or of the previous vote,
+        after that anyone can open it so patrons are never locked out.
+        */
+        pub fn new_with_milestones(
+            goal: Decimal,
+            campaign_duration_epochs: u64,
+            milestones: Vec<(String, Decimal)>,
+            vote_epochs: u64,
+            milestone_epochs: u64,
+        ) -> (ComponentAddress, Bucket) {
+            Self::new_campaign(
+                goal,
+                campaign_duration_epochs,
+                RADIX_TOKEN,
+                milestones,
+                vote_epochs,
+                milestone_epochs,
+                None,
+                Decimal::zero(),
+            )
+        }
+
+        /*
+        Same as new_with_milestones, with pledges paid in payment_resource. Campaigns created by a platform
+        keep platform_fee percent of the withdrawn tokens, collected with the platform_badge.
+        */
+        pub fn new_campaign(
+            goal: Decimal,
+            campaign_duration_epochs: u64,
+            payment_resource: ResourceAddress,
+            milestones: Vec<(String, Decimal)>,
+            vote_epochs: u64,
+            milestone_epochs: u64,
+            platform_badge: Option<ResourceAddress>,
+            platform_fee: Decimal,
+        ) -> (ComponentAddress, Bucket) {
+            assert!(
+                matches!(borrow_resource_manager!(payment_resource).resource_type(), ResourceType::Fungible { .. }),
+                "pledges must be paid with a fungible resource."
+            );
+            assert!(
+                platform_fee >= Decimal::zero() && platform_fee < dec!("100"),
+                "platform fee must be in [0, 100)."
+            );
+            assert!(
+                platform_badge.is_some() || platform_fee.is_zero(),
+                "platform fee needs a platform badge."
+            );
+
+            let mut total_percent = Decimal::zero();
+            for (_, percent) in milestones.iter() {
+                assert!(*percent > Decimal::zero(), "milestone percentage must be positive.");
+                total_percent = total_percent + *percent;
+            }
+            assert!(
+                milestones.is_empty() || total_percent == dec!("100"),
+                "milestone percentages must add up to 100."
+            );
+            assert!(
+                milestones.is_empty() || vote_epochs > 0,
+                "milestones need at least one epoch to vote."
+            );
+            assert!(
+                milestones.is_empty() || milestone_epochs > 0,
+                "milestones need at least one epoch to open their vote."
+            );
+            let milestones = milestones
+                .into_iter()
+                .map(|(description, percent)| Milestone {
+                    description: description,
+                    percent: percent,
+                    status: MilestoneStatus::Pending,
+                    votes_for: Decimal::zero(),
+                    votes_against: Decimal::zero(),
+                    voters: Vec::new(),
+                })
+                .collect();
+
             // Create a badge for the fundraiser of the crowdsourcing campaign.
             let fundraiser_badge = ResourceBuilder::new_fungible()
                 .divisibility(DIVISIBILITY_NONE)
@@ -37,19 +215,43 @@ blueprint! {
                 .metadata("name", "patron_mint_badge")
                 .initial_supply(1);
 
+            // Patron badges, one NFT per pledge with its reward tier.
+            let patron_resource = ResourceBuilder::new_non_fungible()
+                .metadata("name", "patron_badge")
+                .mintable(rule!(require(patron_mint_badge.resource_address())), LOCKED)
+                .burnable(rule!(require(patron_mint_badge.resource_address())), LOCKED)
+                .no_initial_supply();
+
             // Setup the access rules for the component methods
             let access_rules = AccessRules::new()
                 .method("withdraw", rule!(require(fundraiser_badge.resource_address())))
+                .method("start_milestone_vote", rule!(require(fundraiser_badge.resource_address())))
+                .method("add_reward_tier", rule!(require(fundraiser_badge.resource_address())))
+                .method("collect_platform_fee", match platform_badge {
+                    Some(platform_badge) => rule!(require(platform_badge)),
+                    None => rule!(deny_all),
+                })
                 .default(rule!(allow_all));
 
             // Instantiate the CrowdsourcingCampaign component.
             let component = Self {
-                collected_xrd: Vault::new(RADIX_TOKEN),
+                collected_xrd: Vault::new(payment_resource),
                 fundraiser_badge_address: fundraiser_badge.resource_address(),
                 patron_mint_badge: Vault::with_bucket(patron_mint_badge),
+                patron_resource: patron_resource,
+                patron_count: 0,
                 goal: goal,
                 last_epoch: last_epoch,
                 patron_entries: HashMap::new(),
+                reward_tiers: Vec::new(),
+                highest_collected: Decimal::zero(),
+                milestones: milestones,
+                vote_epochs: vote_epochs,
+                milestone_epochs: milestone_epochs,
+                milestone_deadline: last_epoch + milestone_epochs,
+                released_xrd: Vault::new(payment_resource),
+                platform_fee: platform_fee,
+                platform_fees: Vault::new(payment_resource),
             }
             .instantiate()
             .add_access_check(access_rules)
@@ -63,10 +265,7 @@ blueprint! {
         Get status of the campaign.
         */
         pub fn status(&mut self) {
-            let mut pledged = Decimal::zero();
-            for (_, value) in self.patron_entries.iter() {
-                pledged = pledged + *value;
-            }
+            let pledged = self.pledged();
 
             info!(
                 "{} XRD collected from {} patrons",
@@ -85,61 +284,130 @@ blueprint! {
                     self.collected_xrd.amount()
                 );
             }
+
+            for (index, tier) in self.reward_tiers.iter().enumerate() {
+                info!(
+                    "tier {} \"{}\" from {} XRD: {} of {} left{}",
+                    index,
+                    tier.perk,
+                    tier.min_pledge,
+                    tier.quantity - tier.claimed,
+                    tier.quantity,
+                    if self.tier_unlocked(tier) { "" } else { ", locked by a stretch goal" }
+                );
+            }
+
+            for (index, milestone) in self.milestones.iter().enumerate() {
+                info!(
+                    "milestone {} \"{}\" ({}%): {:?}, {} XRD for, {} XRD against",
+                    index,
+                    milestone.description,
+                    milestone.percent,
+                    milestone.status,
+                    milestone.votes_for,
+                    milestone.votes_against
+                );
+            }
         }
 
         /*
         Pledge XRD and become a patron.
         */
         pub fn pledge(&mut self, payment: Bucket) -> Bucket {
+            self.mint_patron(payment, None)
+        }
+
+        /*
+        Pledge XRD for a reward tier, the patron badge records the tier and its perk.
+        The tier must have perks left, and its stretch goal must be reached.
+        */
+        pub fn pledge_for_tier(&mut self, payment: Bucket, tier: u64) -> Bucket {
+            let reward_tier = self.reward_tiers.get(tier as usize).expect("no reward tier found.");
+            assert!(self.tier_unlocked(reward_tier), "the stretch goal of this tier is not reached yet.");
+            assert!(reward_tier.claimed < reward_tier.quantity, "no perks left in this tier.");
             assert!(
-                payment.amount() != Decimal::zero(),
-                "you need to pay at least one XRD to become a patron."
+                payment.amount() >= reward_tier.min_pledge,
+                "pledge is lower than the minimum of this tier."
             );
+
+            self.reward_tiers[tier as usize].claimed += 1;
+            self.mint_patron(payment, Some(tier))
+        }
+
+        /*
+        As fundraiser, add a reward tier before the campaign ends. Tiers with a stretch goal
+        are offered once the campaign collected that much XRD.
+        */
+        pub fn add_reward_tier(&mut self, perk: String, min_pledge: Decimal, quantity: u64, stretch_goal: Option<Decimal>) {
             assert!(
                 Runtime::current_epoch() < self.last_epoch,
                 "campaign has already ended."
             );
+            assert!(min_pledge > Decimal::zero(), "minimum pledge must be positive.");
+            assert!(quantity > 0, "quantity must be positive.");
 
-            // Create a burnable badge.
-            let mut patron_badge_resource = ResourceBuilder::new_fungible()
-                .divisibility(DIVISIBILITY_NONE)
-                .metadata("name", "patron_badge")
-                .mintable(rule!(require(self.patron_mint_badge.resource_address())), LOCKED)
-                .burnable(rule!(require(self.patron_mint_badge.resource_address())), LOCKED)
-                .no_initial_supply();
-
-            let patron_badge = self
-                .patron_mint_badge
-                .authorize(|| borrow_resource_manager!(patron_badge_resource).mint(1));
-
-            // Add badge and value to patron entries for this pledge.
-            self.patron_entries
-                .insert(patron_badge.resource_address(), payment.amount());
-
-            // Put payment in collected XRD.
-            self.collected_xrd.put(payment);
+            self.reward_tiers.push(RewardTier {
+                perk: perk,
+                min_pledge: min_pledge,
+                quantity: quantity,
+                claimed: 0,
+                stretch_goal: stretch_goal,
+            });
+        }
 
-            // Return badge
-            patron_badge
+        /*
+        Perk, minimum pledge, quantity, perks left, stretch goal and whether it is offered, of each reward tier.
+        */
+        pub fn get_reward_tiers(&self) -> Vec<(String, Decimal, u64, u64, Option<Decimal>, bool)> {
+            self.reward_tiers
+                .iter()
+                .map(|tier| {
+                    (
+                        tier.perk.clone(),
+                        tier.min_pledge,
+                        tier.quantity,
+                        tier.quantity - tier.claimed,
+                        tier.stretch_goal,
+                        self.tier_unlocked(tier),
+                    )
+                })
+                .collect()
         }
 
         /*
-        Recall pledge as a patron. It is allowed as long as goal hasn't been reached and last_epoch hasn't been passed.
+        Recall pledge as a patron. It is allowed as long as goal hasn't been reached and last_epoch hasn't been passed,
+        or after patrons rejected a milestone. Patrons get their pro-rata share of the XRD not released yet.
          */
         pub fn recall_pledge(&mut self, patron_badge: Bucket) -> Bucket {
             assert!(
                 !(Runtime::current_epoch() > self.last_epoch
-                    && self.collected_xrd.amount() > self.goal),
+                    && (self.collected_xrd.amount() > self.goal || self.milestones_started()))
+                    || self.milestone_rejected(),
                 "campaign was successful and has ended."
             );
 
-            let mut refund = Bucket::new(RADIX_TOKEN);
-            match self.patron_entries.get(&patron_badge.resource_address()) {
+            assert!(
+                patron_badge.resource_address() == self.patron_resource,
+                "invalid patron badge."
+            );
+            let patron = patron_badge.non_fungible::<Patron>();
+            let patron_id = patron.id();
+
+            let pledged = self.pledged();
+            let mut refund = Bucket::new(self.collected_xrd.resource_address());
+            match self.patron_entries.get(&patron_id) {
                 Some(&value) => {
-                    // Put XRD into refund bucket.
-                    refund.put(self.collected_xrd.take(value));
-                    // Remove patron entry.
-                    self.patron_entries.remove(&patron_badge.resource_address());
+                    // Put XRD into refund bucket, the whole pledge unless milestones were released.
+                    let amount = round_down(
+                        value * self.collected_xrd.amount() / pledged,
+                        divisibility(self.collected_xrd.resource_address()),
+                    );
+                    refund.put(self.collected_xrd.take(amount));
+                    // Remove patron entry, and give the perk back to its tier.
+                    self.patron_entries.remove(&patron_id);
+                    if let Some(tier) = patron.data().tier {
+                        self.reward_tiers[tier as usize].claimed -= 1;
+                    }
                     // Authorize to burn patron badge.
                     self.patron_mint_badge.authorize(|| patron_badge.burn());
                 }
@@ -154,18 +422,244 @@ blueprint! {
 
         /*
         As fundraiser, withdraw collected XRD if goal has passed, and the last_epoch has passed.
+        With milestones, only the XRD of the approved milestones is withdrawn.
         */
         pub fn withdraw(&mut self) -> Bucket {
+            let mut payout = if !self.milestones.is_empty() {
+                assert!(
+                    !self.released_xrd.is_empty(),
+                    "no approved milestone to withdraw."
+                );
+                self.released_xrd.take_all()
+            } else {
+                assert!(
+                    Runtime::current_epoch() > self.last_epoch,
+                    "campaign has not ended yet."
+                );
+                assert!(
+                    self.collected_xrd.amount() >= self.goal,
+                    "campaign did not reach it's goal."
+                );
+                self.collected_xrd.take_all()
+            };
+
+            // Keep the platform fee.
+            let fee = round_down(
+                payout.amount() * self.platform_fee / dec!("100"),
+                divisibility(payout.resource_address()),
+            );
+            self.platform_fees.put(payout.take(fee));
+            payout
+        }
+
+        /*
+        As the platform that created the campaign, collect the fees of the withdrawals.
+        */
+        pub fn collect_platform_fee(&mut self) -> Bucket {
+            self.platform_fees.take_all()
+        }
+
+        /*
+        Status, goal, pledged amount, last epoch and payment resource of the campaign.
+        */
+        pub fn get_summary(&self) -> (CampaignStatus, Decimal, Decimal, u64, ResourceAddress) {
+            let raised = self.pledged();
+            let status = if !(Runtime::current_epoch() > self.last_epoch) {
+                CampaignStatus::Active
+            } else if raised >= self.goal && !self.milestone_rejected() {
+                CampaignStatus::Succeeded
+            } else {
+                CampaignStatus::Failed
+            };
+            (status, self.goal, raised, self.last_epoch, self.collected_xrd.resource_address())
+        }
+
+        /*
+        As fundraiser, open the vote on the next milestone once the campaign ended successfully.
+        */
+        pub fn start_milestone_vote(&mut self) {
+            self.open_milestone_vote();
+        }
+
+        /*
+        Open the vote on the next milestone when the fundraiser did not open it in time, anyone can do it.
+        Patrons can then reject the milestone and recall what is left of their pledge.
+        */
+        pub fn start_overdue_milestone_vote(&mut self) {
+            assert!(
+                Runtime::current_epoch() > self.milestone_deadline,
+                "the fundraiser can still open the vote."
+            );
+            self.open_milestone_vote();
+        }
+
+        fn open_milestone_vote(&mut self) {
             assert!(
                 Runtime::current_epoch() > self.last_epoch,
                 "campaign has not ended yet."
             );
             assert!(
-                self.collected_xrd.amount() >= self.goal,
+                self.collected_xrd.amount() >= self.goal || self.milestones_started(),
                 "campaign did not reach it's goal."
             );
+            assert!(!self.milestone_rejected(), "a milestone was rejected.");
+            assert!(
+                !self.milestones.iter().any(|milestone| matches!(milestone.status, MilestoneStatus::Voting(_))),
+                "a milestone vote is already open."
+            );
+
+            let vote_end = Runtime::current_epoch() + self.vote_epochs;
+            match self.milestones.iter_mut().find(|milestone| milestone.status == MilestoneStatus::Pending) {
+                Some(milestone) => milestone.status = MilestoneStatus::Voting(vote_end),
+                None => panic!("no milestone left."),
+            }
+        }
+
+        /*
+        As patron, vote for or against the milestone being voted on with a proof of the patron badge.
+        The vote weighs the pledged XRD.
+        */
+        pub fn vote(&mut self, patron_badge: Proof, approve: bool) {
+            assert_eq!(patron_badge.resource_address(), self.patron_resource, "invalid patron badge.");
+            assert_eq!(patron_badge.amount(), dec!("1"), "invalid patron badge.");
+            let patron = patron_badge.non_fungible::<Patron>().id();
+            let pledge = match self.patron_entries.get(&patron) {
+                Some(&value) => value,
+                None => panic!("no pledge found with provided badge."),
+            };
+
+            let milestone = self
+                .milestones
+                .iter_mut()
+                .find(|milestone| matches!(milestone.status, MilestoneStatus::Voting(_)))
+                .expect("no milestone vote is open.");
+            if let MilestoneStatus::Voting(vote_end) = milestone.status {
+                assert!(Runtime::current_epoch() <= vote_end, "the vote has ended.");
+            }
+            assert!(!milestone.voters.contains(&patron), "this patron already voted.");
+
+            milestone.voters.push(patron);
+            if approve {
+                milestone.votes_for = milestone.votes_for + pledge;
+            } else {
+                milestone.votes_against = milestone.votes_against + pledge;
+            }
+        }
+
+        /*
+        Close the vote on a milestone after its last epoch, anyone can do it. The milestone is approved
+        with more pledged XRD for than against, and its portion of the XRD is released to the fundraiser.
+        Otherwise patrons can recall what is left of their pledge.
+        */
+        pub fn close_milestone_vote(&mut self) {
+            let divisibility = divisibility(self.collected_xrd.resource_address());
+            let remaining_percent: Decimal = self
+                .milestones
+                .iter()
+                .filter(|milestone| matches!(milestone.status, MilestoneStatus::Pending | MilestoneStatus::Voting(_)))
+                .fold(Decimal::zero(), |total, milestone| total + milestone.percent);
+
+            let milestone = self
+                .milestones
+                .iter_mut()
+                .find(|milestone| matches!(milestone.status, MilestoneStatus::Voting(_)))
+                .expect("no milestone vote is open.");
+            if let MilestoneStatus::Voting(vote_end) = milestone.status {
+                assert!(Runtime::current_epoch() > vote_end, "the vote has not ended yet.");
+            }
+
+            // The fundraiser has milestone_epochs to open the next vote.
+            self.milestone_deadline = Runtime::current_epoch() + self.milestone_epochs;
+            if milestone.votes_for > milestone.votes_against {
+                milestone.status = MilestoneStatus::Approved;
+                // The portion of what is left, the last milestone gets everything.
+                let amount = round_down(self.collected_xrd.amount() * milestone.percent / remaining_percent, divisibility);
+                self.released_xrd.put(self.collected_xrd.take(amount));
+            } else {
+                milestone.status = MilestoneStatus::Rejected;
+            }
+        }
+
+        /*
+        Description, percentage, status and pledged XRD for and against of each milestone.
+        */
+        pub fn get_milestones(&self) -> Vec<(String, Decimal, MilestoneStatus, Decimal, Decimal)> {
+            self.milestones
+                .iter()
+                .map(|milestone| {
+                    (
+                        milestone.description.clone(),
+                        milestone.percent,
+                        milestone.status.clone(),
+                        milestone.votes_for,
+                        milestone.votes_against,
+                    )
+                })
+                .collect()
+        }
+
+        fn mint_patron(&mut self, payment: Bucket, tier: Option<u64>) -> Bucket {
+            assert!(
+                payment.resource_address() == self.collected_xrd.resource_address(),
+                "pledges must be paid with the payment resource of the campaign."
+            );
+            assert!(
+                payment.amount() != Decimal::zero(),
+                "you need to pay at least one XRD to become a patron."
+            );
+            assert!(
+                Runtime::current_epoch() < self.last_epoch,
+                "campaign has already ended."
+            );
+
+            // Mint a burnable badge with the pledge and the perk.
+            let patron_id = NonFungibleId::from_u64(self.patron_count);
+            self.patron_count += 1;
+            let patron = Patron {
+                pledge: payment.amount(),
+                tier: tier,
+                perk: tier.map(|tier| self.reward_tiers[tier as usize].perk.clone()),
+            };
+            let patron_resource = self.patron_resource;
+            let patron_badge = self
+                .patron_mint_badge
+                .authorize(|| borrow_resource_manager!(patron_resource).mint_non_fungible(&patron_id, patron));
+
+            // Add badge and value to patron entries for this pledge.
+            self.patron_entries.insert(patron_id, payment.amount());
+
+            // Put payment in collected XRD, it may unlock stretch goals.
+            self.collected_xrd.put(payment);
+            if self.collected_xrd.amount() > self.highest_collected {
+                self.highest_collected = self.collected_xrd.amount();
+            }
+
+            // Return badge
+            patron_badge
+        }
+
+        fn tier_unlocked(&self, tier: &RewardTier) -> bool {
+            match tier.stretch_goal {
+                Some(stretch_goal) => self.highest_collected >= stretch_goal,
+                None => true,
+            }
+        }
+
+        // Total XRD pledged by the patrons who did not recall their pledge.
+        fn pledged(&self) -> Decimal {
+            let mut pledged = Decimal::zero();
+            for (_, value) in self.patron_entries.iter() {
+                pledged = pledged + *value;
+            }
+            pledged
+        }
+
+        fn milestones_started(&self) -> bool {
+            self.milestones.iter().any(|milestone| milestone.status != MilestoneStatus::Pending)
+        }
 
-            self.collected_xrd.take_all()
+        fn milestone_rejected(&self) -> bool {
+            self.milestones.iter().any(|milestone| milestone.status == MilestoneStatus::Rejected)
         }
     }
 }
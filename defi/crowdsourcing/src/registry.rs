@@ -0,0 +1,141 @@
+use crate::{CampaignStatus, CrowdsourcingCampaign};
+use scrypto::prelude::*;
+
+blueprint! {
+    struct CampaignRegistry {
+        // Definition of the admin badge, to collect the platform fees.
+        admin_badge_address: ResourceAddress,
+        // Collects the platform fees from the campaigns.
+        platform_badge: Vault,
+        // Percentage of the withdrawn tokens kept on the campaigns created from now on.
+        platform_fee: Decimal,
+        // All campaigns created by the registry.
+        campaigns: Vec<ComponentAddress>,
+        // Collected platform fees, per payment resource.
+        fees: HashMap<ResourceAddress, Vault>,
+    }
+
+    impl CampaignRegistry {
+        /*
+        Setup the registry with the platform fee in percents, charged on what fundraisers withdraw
+        from successful campaigns. Returns the admin badge.
+        */
+        pub fn new(platform_fee: Decimal) -> (ComponentAddress, Bucket) {
+            assert!(
+                platform_fee >= Decimal::zero() && platform_fee < dec!("100"),
+                "platform fee must be in [0, 100)."
+            );
+
+            let admin_badge = ResourceBuilder::new_fungible()
+                .divisibility(DIVISIBILITY_NONE)
+                .metadata("name", "registry_admin_badge")
+                .initial_supply(1);
+
+            let platform_badge = ResourceBuilder::new_fungible()
+                .divisibility(DIVISIBILITY_NONE)
+                .metadata("name", "platform_badge")
+                .initial_supply(1);
+
+            let access_rules = AccessRules::new()
+                .method("collect_fees", rule!(require(admin_badge.resource_address())))
+                .method("set_platform_fee", rule!(require(admin_badge.resource_address())))
+                .default(rule!(allow_all));
+
+            let component = Self {
+                admin_badge_address: admin_badge.resource_address(),
+                platform_badge: Vault::with_bucket(platform_badge),
+                platform_fee: platform_fee,
+                campaigns: Vec::new(),
+                fees: HashMap::new(),
+            }
+            .instantiate()
+            .add_access_check(access_rules)
+            .globalize();
+
+            (component, admin_badge)
+        }
+
+        /*
+        Create a campaign paid in payment_resource, milestones can be empty (see CrowdsourcingCampaign::new_with_milestones).
+        Returns the campaign and its fundraiser badge.
+        */
+        pub fn create_campaign(
+            &mut self,
+            goal: Decimal,
+            campaign_duration_epochs: u64,
+            payment_resource: ResourceAddress,
+            milestones: Vec<(String, Decimal)>,
+            vote_epochs: u64,
+            milestone_epochs: u64,
+        ) -> (ComponentAddress, Bucket) {
+            let (campaign, fundraiser_badge) = CrowdsourcingCampaign::new_campaign(
+                goal,
+                campaign_duration_epochs,
+                payment_resource,
+                milestones,
+                vote_epochs,
+                milestone_epochs,
+                Some(self.platform_badge.resource_address()),
+                self.platform_fee,
+            );
+            self.campaigns.push(campaign);
+
+            (campaign, fundraiser_badge)
+        }
+
+        /*
+        Address, status, goal, pledged amount, last epoch and payment resource of every campaign.
+        */
+        pub fn get_campaigns(&self) -> Vec<(ComponentAddress, CampaignStatus, Decimal, Decimal, u64, ResourceAddress)> {
+            self.campaigns
+                .iter()
+                .map(|campaign| {
+                    let (status, goal, raised, last_epoch, payment_resource) = borrow_component!(*campaign)
+                        .call::<(CampaignStatus, Decimal, Decimal, u64, ResourceAddress)>("get_summary", args![]);
+                    (*campaign, status, goal, raised, last_epoch, payment_resource)
+                })
+                .collect()
+        }
+
+        /*
+        Same as get_campaigns, for the active, succeeded or failed campaigns only.
+        */
+        pub fn get_campaigns_by_status(
+            &self,
+            status: CampaignStatus,
+        ) -> Vec<(ComponentAddress, CampaignStatus, Decimal, Decimal, u64, ResourceAddress)> {
+            self.get_campaigns()
+                .into_iter()
+                .filter(|campaign| campaign.1 == status)
+                .collect()
+        }
+
+        /*
+        As admin, change the platform fee of the campaigns created from now on.
+        */
+        pub fn set_platform_fee(&mut self, platform_fee: Decimal) {
+            assert!(
+                platform_fee >= Decimal::zero() && platform_fee < dec!("100"),
+                "platform fee must be in [0, 100)."
+            );
+            self.platform_fee = platform_fee;
+        }
+
+        /*
+        As admin, collect the platform fees of all campaigns, one bucket per payment resource.
+        */
+        pub fn collect_fees(&mut self) -> Vec<Bucket> {
+            for campaign in self.campaigns.iter() {
+                let fee = self
+                    .platform_badge
+                    .authorize(|| borrow_component!(*campaign).call::<Bucket>("collect_platform_fee", args![]));
+                self.fees
+                    .entry(fee.resource_address())
+                    .or_insert_with(|| Vault::new(fee.resource_address()))
+                    .put(fee);
+            }
+
+            self.fees.values_mut().map(|fees| fees.take_all()).collect()
+        }
+    }
+}
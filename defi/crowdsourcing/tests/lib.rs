@@ -0,0 +1,499 @@
+use crowdsourcing::{CampaignStatus, MilestoneStatus};
+use radix_engine::ledger::*;
+use radix_engine::transaction::*;
+use scrypto::prelude::*;
+
+struct Account {
+    pk: EcdsaPublicKey,
+    sk: EcdsaPrivateKey,
+    address: ComponentAddress,
+}
+
+struct Setup {
+    fundraiser: Account,
+    patrons: Vec<Account>,
+    campaign: ComponentAddress,
+    fundraiser_badge: ResourceAddress,
+    patron_resource: ResourceAddress,
+}
+
+fn execute(executor: &mut TransactionExecutor<InMemorySubstateStore>, account: &Account, builder: &mut TransactionBuilder) -> Receipt {
+    let transaction = builder
+        .call_method_with_all_resources(account.address, "deposit_batch")
+        .build(executor.get_nonce([account.pk]))
+        .sign([&account.sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    println!("{:?}\n", receipt);
+    receipt
+}
+
+fn run(executor: &mut TransactionExecutor<InMemorySubstateStore>, account: &Account, builder: &mut TransactionBuilder) -> Receipt {
+    let receipt = execute(executor, account, builder);
+    assert!(receipt.result.is_ok());
+    receipt
+}
+
+fn balance(executor: &mut TransactionExecutor<InMemorySubstateStore>, account: &Account, resource: ResourceAddress) -> Decimal {
+    let receipt = run(executor, account, TransactionBuilder::new().call_method(account.address, "balance", args![resource]));
+    scrypto_decode(&receipt.outputs[0].raw[..]).unwrap()
+}
+
+fn new_account(executor: &mut TransactionExecutor<InMemorySubstateStore>) -> Account {
+    let (pk, sk, address) = executor.new_account();
+    Account { pk, sk, address }
+}
+
+// Campaign of 1000 XRD over 10 epochs with milestones of 40% and 60%, 5 epochs to vote on each and 20 epochs
+// to open each vote, and a patron per pledge
+fn setup(executor: &mut TransactionExecutor<InMemorySubstateStore>, pledges: &[Decimal]) -> Setup {
+    let fundraiser = new_account(executor);
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let milestones = vec![("prototype".to_string(), dec!("40")), ("release".to_string(), dec!("60"))];
+    let receipt = run(
+        executor,
+        &fundraiser,
+        TransactionBuilder::new().call_function(
+            package,
+            "CrowdsourcingCampaign",
+            "new_with_milestones",
+            args![dec!("1000"), 10u64, milestones, 5u64, 20u64],
+        ),
+    );
+    let campaign = receipt.new_component_addresses[0];
+    // fundraiser badge, patron mint badge and patron badge resources
+    let fundraiser_badge = receipt.new_resource_addresses[0];
+    let patron_resource = receipt.new_resource_addresses[2];
+
+    let mut setup = Setup { fundraiser, patrons: Vec::new(), campaign, fundraiser_badge, patron_resource };
+    for amount in pledges {
+        let patron = new_account(executor);
+        assert!(pledge(executor, &setup, &patron, *amount).result.is_ok());
+        setup.patrons.push(patron);
+    }
+    setup
+}
+
+fn pledge(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Setup, patron: &Account, amount: Decimal) -> Receipt {
+    execute(
+        executor,
+        patron,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(amount, RADIX_TOKEN, patron.address)
+            .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+                builder.call_method(setup.campaign, "pledge", args![scrypto::resource::Bucket(bucket_id)])
+            }),
+    )
+}
+
+fn call(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Setup, account: &Account, method: &str) -> Receipt {
+    execute(executor, account, TransactionBuilder::new().call_method(setup.campaign, method, args![]))
+}
+
+fn as_fundraiser(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Setup, method: &str) -> Receipt {
+    execute(
+        executor,
+        &setup.fundraiser,
+        TransactionBuilder::new()
+            .create_proof_from_account_by_amount(dec!("1"), setup.fundraiser_badge, setup.fundraiser.address)
+            .call_method(setup.campaign, method, args![]),
+    )
+}
+
+fn vote(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Setup, patron: &Account, approve: bool) -> Receipt {
+    execute(
+        executor,
+        patron,
+        TransactionBuilder::new()
+            .create_proof_from_account_by_amount(dec!("1"), setup.patron_resource, patron.address)
+            .pop_from_auth_zone(|builder, proof_id| {
+                builder.call_method(setup.campaign, "vote", args![scrypto::resource::Proof(proof_id), approve])
+            }),
+    )
+}
+
+fn recall(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Setup, patron: &Account) -> Receipt {
+    execute(
+        executor,
+        patron,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(dec!("1"), setup.patron_resource, patron.address)
+            .take_from_worktop(setup.patron_resource, |builder, bucket_id| {
+                builder.call_method(setup.campaign, "recall_pledge", args![scrypto::resource::Bucket(bucket_id)])
+            }),
+    )
+}
+
+fn milestone_status(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Setup, index: usize) -> MilestoneStatus {
+    let receipt = run(executor, &setup.fundraiser, TransactionBuilder::new().call_method(setup.campaign, "get_milestones", args![]));
+    let milestones: Vec<(String, Decimal, MilestoneStatus, Decimal, Decimal)> = scrypto_decode(&receipt.outputs[0].raw[..]).unwrap();
+    milestones[index].2.clone()
+}
+
+// Open the vote on the next milestone at `epoch`, vote and close it after the 5 epochs of the vote
+fn vote_on_milestone(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Setup, epoch: u64, votes: &[bool]) {
+    executor.substate_store_mut().set_epoch(epoch);
+    assert!(as_fundraiser(executor, setup, "start_milestone_vote").result.is_ok());
+    for (patron, approve) in setup.patrons.iter().zip(votes) {
+        assert!(vote(executor, setup, patron, *approve).result.is_ok());
+    }
+    executor.substate_store_mut().set_epoch(epoch + 6);
+    assert!(call(executor, setup, &setup.fundraiser, "close_milestone_vote").result.is_ok());
+}
+
+#[test]
+fn test_approved_milestone_releases_its_portion() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup(&mut executor, &[dec!("600"), dec!("400")]);
+
+    // only the fundraiser opens the vote, and only after the campaign
+    assert!(as_fundraiser(&mut executor, &setup, "start_milestone_vote").result.is_err());
+    executor.substate_store_mut().set_epoch(11);
+    assert!(call(&mut executor, &setup, &setup.patrons[0], "start_milestone_vote").result.is_err());
+
+    // 600 XRD for and 400 against
+    vote_on_milestone(&mut executor, &setup, 11, &[true, false]);
+    assert_eq!(milestone_status(&mut executor, &setup, 0), MilestoneStatus::Approved);
+
+    let before = balance(&mut executor, &setup.fundraiser, RADIX_TOKEN);
+    assert!(as_fundraiser(&mut executor, &setup, "withdraw").result.is_ok());
+    assert_eq!(balance(&mut executor, &setup.fundraiser, RADIX_TOKEN) - before, dec!("400"));
+
+    // the campaign succeeded, patrons can't recall
+    assert!(recall(&mut executor, &setup, &setup.patrons[1]).result.is_err());
+}
+
+#[test]
+fn test_rejected_milestone_lets_patrons_recall_pro_rata() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup(&mut executor, &[dec!("600"), dec!("400")]);
+
+    vote_on_milestone(&mut executor, &setup, 11, &[true, true]);
+    assert!(as_fundraiser(&mut executor, &setup, "withdraw").result.is_ok());
+    vote_on_milestone(&mut executor, &setup, 20, &[false, true]);
+    assert_eq!(milestone_status(&mut executor, &setup, 1), MilestoneStatus::Rejected);
+
+    // 600 XRD are left, shared by what each patron pledged
+    let before = balance(&mut executor, &setup.patrons[0], RADIX_TOKEN);
+    assert!(recall(&mut executor, &setup, &setup.patrons[0]).result.is_ok());
+    assert_eq!(balance(&mut executor, &setup.patrons[0], RADIX_TOKEN) - before, dec!("360"));
+
+    let before = balance(&mut executor, &setup.patrons[1], RADIX_TOKEN);
+    assert!(recall(&mut executor, &setup, &setup.patrons[1]).result.is_ok());
+    assert_eq!(balance(&mut executor, &setup.patrons[1], RADIX_TOKEN) - before, dec!("240"));
+
+    // nothing is left to vote on
+    assert!(as_fundraiser(&mut executor, &setup, "start_milestone_vote").result.is_err());
+}
+
+#[test]
+fn test_tie_rejects_the_milestone() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup(&mut executor, &[dec!("500"), dec!("500")]);
+
+    vote_on_milestone(&mut executor, &setup, 11, &[true, false]);
+    assert_eq!(milestone_status(&mut executor, &setup, 0), MilestoneStatus::Rejected);
+    assert!(as_fundraiser(&mut executor, &setup, "withdraw").result.is_err());
+    assert!(recall(&mut executor, &setup, &setup.patrons[0]).result.is_ok());
+}
+
+#[test]
+fn test_patron_votes_once() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup(&mut executor, &[dec!("600"), dec!("400")]);
+
+    executor.substate_store_mut().set_epoch(11);
+    assert!(as_fundraiser(&mut executor, &setup, "start_milestone_vote").result.is_ok());
+    assert!(vote(&mut executor, &setup, &setup.patrons[1], true).result.is_ok());
+    assert!(vote(&mut executor, &setup, &setup.patrons[1], true).result.is_err());
+    assert!(vote(&mut executor, &setup, &setup.patrons[1], false).result.is_err());
+}
+
+#[test]
+fn test_vote_after_the_end_fails() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup(&mut executor, &[dec!("600"), dec!("400")]);
+
+    executor.substate_store_mut().set_epoch(11);
+    assert!(as_fundraiser(&mut executor, &setup, "start_milestone_vote").result.is_ok());
+    assert!(vote(&mut executor, &setup, &setup.patrons[0], true).result.is_ok());
+
+    // the vote ends at epoch 16, it can only be closed after that
+    executor.substate_store_mut().set_epoch(16);
+    assert!(call(&mut executor, &setup, &setup.fundraiser, "close_milestone_vote").result.is_err());
+    executor.substate_store_mut().set_epoch(17);
+    assert!(vote(&mut executor, &setup, &setup.patrons[1], false).result.is_err());
+    assert!(call(&mut executor, &setup, &setup.fundraiser, "close_milestone_vote").result.is_ok());
+    assert!(vote(&mut executor, &setup, &setup.patrons[1], false).result.is_err());
+    assert_eq!(milestone_status(&mut executor, &setup, 0), MilestoneStatus::Approved);
+}
+
+#[test]
+fn test_anyone_opens_an_overdue_vote() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup(&mut executor, &[dec!("600"), dec!("400")]);
+
+    // the fundraiser has until epoch 30 to open the vote
+    executor.substate_store_mut().set_epoch(30);
+    assert!(call(&mut executor, &setup, &setup.patrons[0], "start_overdue_milestone_vote").result.is_err());
+    executor.substate_store_mut().set_epoch(31);
+    assert!(call(&mut executor, &setup, &setup.patrons[0], "start_overdue_milestone_vote").result.is_ok());
+    assert!(vote(&mut executor, &setup, &setup.patrons[0], false).result.is_ok());
+
+    executor.substate_store_mut().set_epoch(37);
+    assert!(call(&mut executor, &setup, &setup.patrons[0], "close_milestone_vote").result.is_ok());
+    let before = balance(&mut executor, &setup.patrons[0], RADIX_TOKEN);
+    assert!(recall(&mut executor, &setup, &setup.patrons[0]).result.is_ok());
+    assert_eq!(balance(&mut executor, &setup.patrons[0], RADIX_TOKEN) - before, dec!("600"));
+}
+
+// Campaign of 1000 XRD over 10 epochs without milestones, and two patrons who did not pledge yet
+fn setup_tiers(executor: &mut TransactionExecutor<InMemorySubstateStore>) -> Setup {
+    let fundraiser = new_account(executor);
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let receipt = run(
+        executor,
+        &fundraiser,
+        TransactionBuilder::new().call_function(package, "CrowdsourcingCampaign", "new", args![dec!("1000"), 10u64]),
+    );
+    let campaign = receipt.new_component_addresses[0];
+    // fundraiser badge, patron mint badge and patron badge resources
+    let fundraiser_badge = receipt.new_resource_addresses[0];
+    let patron_resource = receipt.new_resource_addresses[2];
+
+    let patrons = vec![new_account(executor), new_account(executor)];
+    Setup { fundraiser, patrons, campaign, fundraiser_badge, patron_resource }
+}
+
+fn add_reward_tier(
+    executor: &mut TransactionExecutor<InMemorySubstateStore>,
+    setup: &Setup,
+    perk: &str,
+    min_pledge: Decimal,
+    quantity: u64,
+    stretch_goal: Option<Decimal>,
+) {
+    run(
+        executor,
+        &setup.fundraiser,
+        TransactionBuilder::new()
+            .create_proof_from_account_by_amount(dec!("1"), setup.fundraiser_badge, setup.fundraiser.address)
+            .call_method(setup.campaign, "add_reward_tier", args![perk.to_string(), min_pledge, quantity, stretch_goal]),
+    );
+}
+
+fn pledge_for_tier(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Setup, patron: &Account, amount: Decimal, tier: u64) -> Receipt {
+    execute(
+        executor,
+        patron,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(amount, RADIX_TOKEN, patron.address)
+            .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+                builder.call_method(setup.campaign, "pledge_for_tier", args![scrypto::resource::Bucket(bucket_id), tier])
+            }),
+    )
+}
+
+// Perks left and whether it is offered, of each reward tier
+fn tiers(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Setup) -> Vec<(u64, bool)> {
+    let receipt = run(executor, &setup.fundraiser, TransactionBuilder::new().call_method(setup.campaign, "get_reward_tiers", args![]));
+    let tiers: Vec<(String, Decimal, u64, u64, Option<Decimal>, bool)> = scrypto_decode(&receipt.outputs[0].raw[..]).unwrap();
+    tiers.into_iter().map(|tier| (tier.3, tier.5)).collect()
+}
+
+#[test]
+fn test_tier_quantity_is_limited_and_recall_gives_the_perk_back() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup_tiers(&mut executor);
+    add_reward_tier(&mut executor, &setup, "signed copy", dec!("100"), 1, None);
+
+    assert!(pledge_for_tier(&mut executor, &setup, &setup.patrons[1], dec!("99"), 0).result.is_err());
+    assert!(pledge_for_tier(&mut executor, &setup, &setup.patrons[0], dec!("100"), 0).result.is_ok());
+    assert_eq!(tiers(&mut executor, &setup), vec![(0, true)]);
+    assert!(pledge_for_tier(&mut executor, &setup, &setup.patrons[1], dec!("150"), 0).result.is_err());
+
+    assert!(recall(&mut executor, &setup, &setup.patrons[0]).result.is_ok());
+    assert_eq!(tiers(&mut executor, &setup), vec![(1, true)]);
+    assert!(pledge_for_tier(&mut executor, &setup, &setup.patrons[1], dec!("150"), 0).result.is_ok());
+    assert_eq!(tiers(&mut executor, &setup), vec![(0, true)]);
+}
+
+#[test]
+fn test_stretch_goal_stays_unlocked_after_recalls() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup_tiers(&mut executor);
+    add_reward_tier(&mut executor, &setup, "launch party", dec!("10"), 10, Some(dec!("500")));
+
+    assert_eq!(tiers(&mut executor, &setup), vec![(10, false)]);
+    assert!(pledge_for_tier(&mut executor, &setup, &setup.patrons[1], dec!("10"), 0).result.is_err());
+
+    // 500 XRD were collected once, the tier stays offered when they are recalled
+    assert!(pledge(&mut executor, &setup, &setup.patrons[0], dec!("500")).result.is_ok());
+    assert!(recall(&mut executor, &setup, &setup.patrons[0]).result.is_ok());
+    assert_eq!(tiers(&mut executor, &setup), vec![(10, true)]);
+    assert!(pledge_for_tier(&mut executor, &setup, &setup.patrons[1], dec!("10"), 0).result.is_ok());
+}
+
+struct RegistrySetup {
+    account: Account,
+    registry: ComponentAddress,
+    admin_badge: ResourceAddress,
+    // an indivisible payment resource, so amounts need rounding
+    gold: ResourceAddress,
+}
+
+// Registry with a 2.5% platform fee, and 10 000 GLD in the account
+fn setup_registry(executor: &mut TransactionExecutor<InMemorySubstateStore>) -> RegistrySetup {
+    let account = new_account(executor);
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let receipt = run(executor, &account, TransactionBuilder::new().call_function(package, "CampaignRegistry", "new", args![dec!("2.5")]));
+    let registry = receipt.new_component_addresses[0];
+    // admin badge and platform badge resources
+    let admin_badge = receipt.new_resource_addresses[0];
+
+    let mut metadata = HashMap::new();
+    metadata.insert("name".to_string(), "Gold".to_string());
+    metadata.insert("symbol".to_string(), "GLD".to_string());
+    let receipt = run(executor, &account, TransactionBuilder::new().new_badge_fixed(metadata, dec!("10000")));
+    let gold = receipt.new_resource_addresses[0];
+
+    RegistrySetup { account, registry, admin_badge, gold }
+}
+
+// Create a campaign of `goal` over 10 epochs paid in `payment_resource`, with 5 epochs to vote on each milestone
+// and 20 epochs to open each vote. The account is the fundraiser
+fn create_campaign(
+    executor: &mut TransactionExecutor<InMemorySubstateStore>,
+    setup: &RegistrySetup,
+    goal: Decimal,
+    payment_resource: ResourceAddress,
+    milestones: Vec<(String, Decimal)>,
+) -> (ComponentAddress, ResourceAddress, ResourceAddress) {
+    let receipt = run(
+        executor,
+        &setup.account,
+        TransactionBuilder::new().call_method(
+            setup.registry,
+            "create_campaign",
+            args![goal, 10u64, payment_resource, milestones, 5u64, 20u64],
+        ),
+    );
+    // fundraiser badge, patron mint badge and patron badge resources
+    (receipt.new_component_addresses[0], receipt.new_resource_addresses[0], receipt.new_resource_addresses[2])
+}
+
+fn campaigns_by_status(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &RegistrySetup, status: CampaignStatus) -> Vec<ComponentAddress> {
+    let receipt = run(
+        executor,
+        &setup.account,
+        TransactionBuilder::new().call_method(setup.registry, "get_campaigns_by_status", args![status]),
+    );
+    let campaigns: Vec<(ComponentAddress, CampaignStatus, Decimal, Decimal, u64, ResourceAddress)> =
+        scrypto_decode(&receipt.outputs[0].raw[..]).unwrap();
+    campaigns.into_iter().map(|campaign| campaign.0).collect()
+}
+
+fn pledge_in(executor: &mut TransactionExecutor<InMemorySubstateStore>, account: &Account, campaign: ComponentAddress, resource: ResourceAddress, amount: Decimal) {
+    run(
+        executor,
+        account,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(amount, resource, account.address)
+            .take_from_worktop(resource, |builder, bucket_id| {
+                builder.call_method(campaign, "pledge", args![scrypto::resource::Bucket(bucket_id)])
+            }),
+    );
+}
+
+#[test]
+fn test_registry_lists_campaigns_by_status_and_collects_rounded_fees() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup_registry(&mut executor);
+
+    let (gold_campaign, fundraiser_badge, _) = create_campaign(&mut executor, &setup, dec!("100"), setup.gold, Vec::new());
+    let (xrd_campaign, _, _) = create_campaign(&mut executor, &setup, dec!("1000"), RADIX_TOKEN, Vec::new());
+    pledge_in(&mut executor, &setup.account, gold_campaign, setup.gold, dec!("150"));
+    pledge_in(&mut executor, &setup.account, xrd_campaign, RADIX_TOKEN, dec!("10"));
+    assert_eq!(campaigns_by_status(&mut executor, &setup, CampaignStatus::Active), vec![gold_campaign, xrd_campaign]);
+
+    executor.substate_store_mut().set_epoch(11);
+    assert_eq!(campaigns_by_status(&mut executor, &setup, CampaignStatus::Active), Vec::<ComponentAddress>::new());
+    assert_eq!(campaigns_by_status(&mut executor, &setup, CampaignStatus::Succeeded), vec![gold_campaign]);
+    assert_eq!(campaigns_by_status(&mut executor, &setup, CampaignStatus::Failed), vec![xrd_campaign]);
+
+    // the fee of 3.75 GLD is rounded down to 3
+    let before = balance(&mut executor, &setup.account, setup.gold);
+    run(
+        &mut executor,
+        &setup.account,
+        TransactionBuilder::new()
+            .create_proof_from_account_by_amount(dec!("1"), fundraiser_badge, setup.account.address)
+            .call_method(gold_campaign, "withdraw", args![]),
+    );
+    assert_eq!(balance(&mut executor, &setup.account, setup.gold) - before, dec!("147"));
+
+    // only the admin collects the fees, and only the platform badge of the registry takes them from the campaigns
+    let receipt = execute(&mut executor, &setup.account, TransactionBuilder::new().call_method(setup.registry, "collect_fees", args![]));
+    assert!(receipt.result.is_err());
+    let receipt = execute(&mut executor, &setup.account, TransactionBuilder::new().call_method(gold_campaign, "collect_platform_fee", args![]));
+    assert!(receipt.result.is_err());
+    run(
+        &mut executor,
+        &setup.account,
+        TransactionBuilder::new()
+            .create_proof_from_account_by_amount(dec!("1"), setup.admin_badge, setup.account.address)
+            .call_method(setup.registry, "collect_fees", args![]),
+    );
+    assert_eq!(balance(&mut executor, &setup.account, setup.gold) - before, dec!("150"));
+}
+
+#[test]
+fn test_milestone_release_and_recall_are_rounded() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let registry = setup_registry(&mut executor);
+    let gold = registry.gold;
+
+    let milestones = vec![("prototype".to_string(), dec!("33")), ("release".to_string(), dec!("67"))];
+    let (campaign, fundraiser_badge, patron_resource) = create_campaign(&mut executor, &registry, dec!("100"), gold, milestones);
+
+    // patrons pledge 60 and 41 GLD
+    let mut patrons = Vec::new();
+    for amount in [dec!("60"), dec!("41")] {
+        let patron = new_account(&mut executor);
+        run(
+            &mut executor,
+            &registry.account,
+            TransactionBuilder::new()
+                .withdraw_from_account_by_amount(amount, gold, registry.account.address)
+                .call_method_with_all_resources(patron.address, "deposit_batch"),
+        );
+        pledge_in(&mut executor, &patron, campaign, gold, amount);
+        patrons.push(patron);
+    }
+    let setup = Setup { fundraiser: registry.account, patrons, campaign, fundraiser_badge, patron_resource };
+
+    // 33.33 GLD are released, rounded down to 33, and the fee of 0.825 GLD to 0
+    vote_on_milestone(&mut executor, &setup, 11, &[true, true]);
+    let before = balance(&mut executor, &setup.fundraiser, gold);
+    assert!(as_fundraiser(&mut executor, &setup, "withdraw").result.is_ok());
+    assert_eq!(balance(&mut executor, &setup.fundraiser, gold) - before, dec!("33"));
+
+    // 68 GLD are left, the first patron gets 40.39 rounded down to 40 and the second what is left
+    vote_on_milestone(&mut executor, &setup, 20, &[false, false]);
+    assert!(recall(&mut executor, &setup, &setup.patrons[0]).result.is_ok());
+    assert_eq!(balance(&mut executor, &setup.patrons[0], gold), dec!("40"));
+    assert!(recall(&mut executor, &setup, &setup.patrons[1]).result.is_ok());
+    assert_eq!(balance(&mut executor, &setup.patrons[1], gold), dec!("28"));
+}
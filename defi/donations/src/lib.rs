@@ -1,12 +1,41 @@
 use scrypto::prelude::*;
 
+// Subscription NFT, held by the donor
+#[derive(NonFungibleData)]
+pub struct Subscription {
+    owner: ComponentAddress,
+    amount_per_period: Decimal,
+    period_epochs: u64,
+    periods: u64,
+    // the first period is due right away, the next ones every `period_epochs`
+    start_epoch: u64,
+    #[scrypto(mutable)]
+    paid_periods: u64,
+    #[scrypto(mutable)]
+    cancelled: bool
+}
+
+impl Subscription {
+    // periods due at `epoch` that were not paid yet
+    fn due_periods(&self, epoch: u64) -> u64 {
+        let due = (epoch - self.start_epoch) / self.period_epochs + 1;
+        due.min(self.periods) - self.paid_periods
+    }
+}
+
 blueprint! {
     struct Donations {
         admin_vault: Vault,
         admin_badge: ResourceAddress,
         fee: Decimal,
         collected_fees: Vault,
-        badges: HashMap<ComponentAddress, Vec<Vault>>
+        badges: HashMap<ComponentAddress, Vec<Vault>>,
+        // subscription NFTs and their pre-funded vaults
+        subscription_resource: ResourceAddress,
+        subscription_count: u64,
+        subscriptions: HashMap<NonFungibleId, Vault>,
+        // subscription ids of each owner
+        owner_subscriptions: HashMap<ComponentAddress, Vec<NonFungibleId>>
     }
 
     impl Donations {
@@ -19,6 +48,13 @@ blueprint! {
             let admin_resource_address = admin_bucket.resource_address();
             let admin_return_bucket: Bucket = admin_bucket.take(1); // Return this badge to the caller
 
+            let subscription_resource = ResourceBuilder::new_non_fungible()
+                .metadata("name", "Donations Subscription")
+                .mintable(rule!(require(admin_resource_address)), LOCKED)
+                .burnable(rule!(require(admin_resource_address)), LOCKED)
+                .updateable_non_fungible_data(rule!(require(admin_resource_address)), LOCKED)
+                .no_initial_supply();
+
             let access_rules = AccessRules::new()
                 .method("withdraw", rule!(require(admin_resource_address)))
                 .default(rule!(allow_all));
@@ -28,7 +64,11 @@ blueprint! {
                 admin_badge: admin_resource_address,
                 collected_fees: Vault::new(RADIX_TOKEN),
                 fee: fee_percent,
-                badges: HashMap::new()
+                badges: HashMap::new(),
+                subscription_resource: subscription_resource,
+                subscription_count: 0,
+                subscriptions: HashMap::new(),
+                owner_subscriptions: HashMap::new()
             }
             .instantiate()
             .globalize();
@@ -112,6 +152,145 @@ blueprint! {
             (badge.take(1), payment)
         }
         
+        // pre-fund a subscription of `amount_per_period` XRD to the owner every `period_epochs` for `periods` periods
+        // returns the subscription NFT and the rest of the payment
+        pub fn subscribe(
+            &mut self,
+            owner: ComponentAddress,
+            amount_per_period: Decimal,
+            period_epochs: u64,
+            periods: u64,
+            mut payment: Bucket
+        ) -> (Bucket, Bucket) {
+            assert!(payment.resource_address() == RADIX_TOKEN.into(), "You must use Radix (XRD).");
+            assert!(amount_per_period > Decimal::zero(), "Amount cannot be zero");
+            assert!(period_epochs > 0, "Period cannot be zero");
+            assert!(periods > 0, "Periods cannot be zero");
+
+            let total = amount_per_period * Decimal::from(periods);
+            assert!(payment.amount() >= total, "Not enough amount");
+
+            let subscription_id = NonFungibleId::from_u64(self.subscription_count);
+            self.subscription_count += 1;
+            let subscription = Subscription {
+                owner: owner,
+                amount_per_period: amount_per_period,
+                period_epochs: period_epochs,
+                periods: periods,
+                start_epoch: Runtime::current_epoch(),
+                paid_periods: 0,
+                cancelled: false
+            };
+
+            let subscription_resource = self.subscription_resource;
+            let subscription_nft = self.admin_vault.authorize(|| {
+                borrow_resource_manager!(subscription_resource).mint_non_fungible(&subscription_id, subscription)
+            });
+
+            self.subscriptions.insert(subscription_id.clone(), Vault::with_bucket(payment.take(total)));
+            self.owner_subscriptions.entry(owner).or_insert(Vec::new()).push(subscription_id);
+
+            (subscription_nft, payment)
+        }
+
+        // transfer the due periods of a subscription to its owner, minus the fee. Anyone can call this
+        pub fn collect_subscription(&mut self, subscription_id: NonFungibleId) {
+            let mut subscription = self.subscription_data(&subscription_id);
+            assert!(!subscription.cancelled, "Subscription is cancelled");
+
+            let due = subscription.due_periods(Runtime::current_epoch());
+            assert!(due > 0, "No period is due");
+            self.pay_periods(&subscription_id, &subscription, due);
+
+            subscription.paid_periods += due;
+            let paid = subscription.paid_periods == subscription.periods;
+            let owner = subscription.owner;
+            self.update_subscription(&subscription_id, subscription);
+
+            // nothing is left to pay, the NFT can still be cancelled for nothing
+            if paid {
+                self.remove_subscription(&subscription_id, owner);
+            }
+        }
+
+        // cancel a subscription with its NFT, the due periods are paid and the unspent balance is returned
+        pub fn cancel_subscription(&mut self, subscription_nft: Bucket) -> Bucket {
+            assert!(subscription_nft.resource_address() == self.subscription_resource, "Invalid subscription provided");
+
+            let subscription_id = subscription_nft.non_fungible::<Subscription>().id();
+            let mut subscription = self.subscription_data(&subscription_id);
+            assert!(!subscription.cancelled, "Subscription is cancelled");
+
+            let due = subscription.due_periods(Runtime::current_epoch());
+            if due > 0 {
+                self.pay_periods(&subscription_id, &subscription, due);
+                subscription.paid_periods += due;
+            }
+
+            subscription.cancelled = true;
+            let owner = subscription.owner;
+            self.update_subscription(&subscription_id, subscription);
+            self.admin_vault.authorize(|| {
+                subscription_nft.burn();
+            });
+
+            match self.remove_subscription(&subscription_id, owner) {
+                Some(mut vault) => vault.take_all(),
+                // fully paid and already removed
+                None => Bucket::new(RADIX_TOKEN)
+            }
+        }
+
+        // (subscription id, amount per period, period epochs, periods left) of the active subscriptions of an owner
+        pub fn get_subscribers(&self, owner: ComponentAddress) -> Vec<(NonFungibleId, Decimal, u64, u64)> {
+            let subscription_ids = match self.owner_subscriptions.get(&owner) {
+                Some(subscription_ids) => subscription_ids.clone(),
+                None => Vec::new()
+            };
+
+            let mut subscribers = Vec::new();
+            for subscription_id in subscription_ids {
+                let subscription = self.subscription_data(&subscription_id);
+                if !subscription.cancelled && subscription.paid_periods < subscription.periods {
+                    let periods_left = subscription.periods - subscription.paid_periods;
+                    subscribers.push((subscription_id, subscription.amount_per_period, subscription.period_epochs, periods_left));
+                }
+            }
+            subscribers
+        }
+
+        // forget a cancelled or fully paid subscription, returns its vault unless it was already removed
+        fn remove_subscription(&mut self, subscription_id: &NonFungibleId, owner: ComponentAddress) -> Option<Vault> {
+            if let Some(subscription_ids) = self.owner_subscriptions.get_mut(&owner) {
+                subscription_ids.retain(|id| id != subscription_id);
+                if subscription_ids.is_empty() {
+                    self.owner_subscriptions.remove(&owner);
+                }
+            }
+            self.subscriptions.remove(subscription_id)
+        }
+
+        fn pay_periods(&mut self, subscription_id: &NonFungibleId, subscription: &Subscription, periods: u64) {
+            let amount = subscription.amount_per_period * Decimal::from(periods);
+            let mut payment = self.subscriptions.get_mut(subscription_id).unwrap().take(amount);
+
+            // Take fee
+            let fee = amount * self.fee / 100;
+            self.collected_fees.put(payment.take(fee));
+            borrow_component!(subscription.owner).call::<()>("deposit", vec![scrypto_encode(&payment)]);
+        }
+
+        fn subscription_data(&self, subscription_id: &NonFungibleId) -> Subscription {
+            borrow_resource_manager!(self.subscription_resource).get_non_fungible_data(subscription_id)
+        }
+
+        fn update_subscription(&self, subscription_id: &NonFungibleId, subscription: Subscription) {
+            let subscription_resource = self.subscription_resource;
+            self.admin_vault.authorize(|| {
+                borrow_resource_manager!(subscription_resource).update_non_fungible_data(subscription_id, subscription)
+            });
+        }
+
         pub fn withdraw(&mut self, amount: Decimal) -> Bucket {
             assert!(self.collected_fees.amount() >= amount, "Withdraw amount is larger than available assets");
 
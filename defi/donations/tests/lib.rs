@@ -72,4 +72,135 @@ fn test_donations() {
     let receipt4 = executor.validate_and_execute(&transaction4).unwrap();
     println!("{:?}\n", receipt4);
     assert!(receipt4.result.is_ok());
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_subscriptions() {
+    // Set up environment.
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+
+    let (pk, sk, account) = executor.new_account();
+    let (owner_pk, owner_sk, owner) = executor.new_account();
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let transaction1 = TransactionBuilder::new()
+        .call_function(package, "Donations", "new", args![dec!("10")])
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt1 = executor.validate_and_execute(&transaction1).unwrap();
+    assert!(receipt1.result.is_ok());
+    let component = receipt1.new_component_addresses[0];
+    let subscription_resource = receipt1.new_resource_addresses[1];
+
+    // Test the `subscribe` method: 4 periods of 10 XRD every 5 epochs, the rest of the payment is returned.
+    let transaction2 = TransactionBuilder::new()
+        .withdraw_from_account_by_amount(dec!("50"), RADIX_TOKEN, account)
+        .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+            builder.call_method(
+                component,
+                "subscribe",
+                args![owner, dec!("10"), 5u64, 4u64, scrypto::resource::Bucket(bucket_id)]
+            )
+        })
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt2 = executor.validate_and_execute(&transaction2).unwrap();
+    println!("{:?}\n", receipt2);
+    assert!(receipt2.result.is_ok());
+
+    let owner_balance = |executor: &mut TransactionExecutor<InMemorySubstateStore>| -> Decimal {
+        let transaction = TransactionBuilder::new()
+            .call_method(owner, "balance", args![RADIX_TOKEN])
+            .build(executor.get_nonce([owner_pk]))
+            .sign([&owner_sk]);
+        let receipt = executor.validate_and_execute(&transaction).unwrap();
+        scrypto_decode(&receipt.outputs[0].raw[..]).unwrap()
+    };
+    let account_balance = |executor: &mut TransactionExecutor<InMemorySubstateStore>| -> Decimal {
+        let transaction = TransactionBuilder::new()
+            .call_method(account, "balance", args![RADIX_TOKEN])
+            .build(executor.get_nonce([pk]))
+            .sign([&sk]);
+        let receipt = executor.validate_and_execute(&transaction).unwrap();
+        scrypto_decode(&receipt.outputs[0].raw[..]).unwrap()
+    };
+    let collect = |executor: &mut TransactionExecutor<InMemorySubstateStore>| -> bool {
+        let transaction = TransactionBuilder::new()
+            .call_method(component, "collect_subscription", args![NonFungibleId::from_u64(0)])
+            .build(executor.get_nonce([pk]))
+            .sign([&sk]);
+        executor.validate_and_execute(&transaction).unwrap().result.is_ok()
+    };
+
+    // Test the `get_subscribers` method.
+    let transaction3 = TransactionBuilder::new()
+        .call_method(component, "get_subscribers", args![owner])
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt3 = executor.validate_and_execute(&transaction3).unwrap();
+    assert!(receipt3.result.is_ok());
+    let subscribers: Vec<(NonFungibleId, Decimal, u64, u64)> = scrypto_decode(&receipt3.outputs[0].raw[..]).unwrap();
+    assert_eq!(subscribers, vec![(NonFungibleId::from_u64(0), dec!("10"), 5u64, 4u64)]);
+
+    // Test the `collect_subscription` method: the first period is due right away, minus the 10% fee.
+    let before = owner_balance(&mut executor);
+    assert!(collect(&mut executor));
+    assert_eq!(owner_balance(&mut executor) - before, dec!("9"));
+    assert!(!collect(&mut executor));
+
+    // Two more periods are due at epoch 10.
+    executor.substate_store_mut().set_epoch(10);
+    let before = owner_balance(&mut executor);
+    assert!(collect(&mut executor));
+    assert_eq!(owner_balance(&mut executor) - before, dec!("18"));
+
+    // Test the `cancel_subscription` method: the last period is returned.
+    let before = account_balance(&mut executor);
+    let transaction4 = TransactionBuilder::new()
+        .withdraw_from_account_by_amount(dec!("1"), subscription_resource, account)
+        .take_from_worktop(subscription_resource, |builder, bucket_id| {
+            builder.call_method(component, "cancel_subscription", args![scrypto::resource::Bucket(bucket_id)])
+        })
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt4 = executor.validate_and_execute(&transaction4).unwrap();
+    println!("{:?}\n", receipt4);
+    assert!(receipt4.result.is_ok());
+    assert_eq!(account_balance(&mut executor) - before, dec!("10"));
+    assert!(!collect(&mut executor));
+
+    // The cancelled subscription is no longer listed.
+    let get_subscribers = |executor: &mut TransactionExecutor<InMemorySubstateStore>| -> Vec<(NonFungibleId, Decimal, u64, u64)> {
+        let transaction = TransactionBuilder::new()
+            .call_method(component, "get_subscribers", args![owner])
+            .build(executor.get_nonce([pk]))
+            .sign([&sk]);
+        let receipt = executor.validate_and_execute(&transaction).unwrap();
+        assert!(receipt.result.is_ok());
+        scrypto_decode(&receipt.outputs[0].raw[..]).unwrap()
+    };
+    assert_eq!(get_subscribers(&mut executor), vec![]);
+
+    // Neither is a fully paid one: a single period, collected right away.
+    let transaction5 = TransactionBuilder::new()
+        .withdraw_from_account_by_amount(dec!("10"), RADIX_TOKEN, account)
+        .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+            builder.call_method(
+                component,
+                "subscribe",
+                args![owner, dec!("10"), 5u64, 1u64, scrypto::resource::Bucket(bucket_id)]
+            )
+        })
+        .call_method(component, "collect_subscription", args![NonFungibleId::from_u64(1)])
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt5 = executor.validate_and_execute(&transaction5).unwrap();
+    println!("{:?}\n", receipt5);
+    assert!(receipt5.result.is_ok());
+    assert_eq!(get_subscribers(&mut executor), vec![]);
+}
@@ -0,0 +1,98 @@
+use sbor::*;
+use scrypto::prelude::*;
+
+/*
+ *  Borrows from a FlashLoan component, routes the loan through a sequence of venues
+ *  and repays the loan out of the result. The whole transaction fails unless the
+ *  route makes at least the requested profit.
+ */
+
+// One step of a route, each step swaps the whole bucket it gets from the previous step
+#[derive(TypeId, Encode, Decode, Describe, Debug, Clone)]
+pub enum Venue {
+    // Market order on an order-book-dex TradingPair, the side follows from the resource of the bucket
+    OrderBook(ComponentAddress),
+    // Swap on a CandyDex into the given resource (XRD or a candy)
+    CandyDex(ComponentAddress, ResourceAddress),
+}
+
+blueprint! {
+    struct ArbitrageExecutor {
+        // Address of the FlashLoan component
+        loaner_component: ComponentAddress
+    }
+
+    impl ArbitrageExecutor {
+        pub fn new(loaner_component_address: ComponentAddress) -> ComponentAddress {
+            Self {
+                loaner_component: loaner_component_address
+            }
+            .instantiate()
+            .globalize()
+        }
+
+        /*
+         * Borrow `amount`, run it through `route` and repay the loan.
+         * Returns the profit and whatever dust the venues handed back along the way
+         */
+        pub fn execute(&self, amount: Decimal, route: Vec<Venue>, minimum_profit: Decimal) -> (Bucket, Vec<Bucket>) {
+            assert!(!route.is_empty(), "The route needs at least one venue");
+            assert!(!minimum_profit.is_negative(), "The minimum profit cannot be negative");
+
+            let flash_loan = borrow_component!(self.loaner_component);
+            let (loan, loan_receipt) = flash_loan.call::<(Bucket, Bucket)>("take_loan", args![amount]);
+            let loaned_resource = loan.resource_address();
+
+            let mut funds = loan;
+            let mut dust: Vec<Bucket> = Vec::new();
+            for venue in route {
+                info!("Swapping {} of {} on {:?}", funds.amount(), funds.resource_address(), venue);
+                let (output, remainder) = swap(venue, funds);
+                if let Some(remainder) = remainder {
+                    if !remainder.is_empty() {
+                        dust.push(remainder);
+                    }
+                }
+                funds = output;
+            }
+            assert!(funds.resource_address() == loaned_resource, "The route has to end in the loaned resource");
+            info!("Route returned {} for a loan of {}", funds.amount(), amount);
+
+            // Repay the loan, the change is the profit
+            let profit: Bucket = flash_loan.call::<Bucket>("repay_loan", args![funds, loan_receipt]);
+            assert!(profit.amount() >= minimum_profit, "Profit of {} is below the minimum of {}", profit.amount(), minimum_profit);
+
+            (profit, dust)
+        }
+    }
+}
+
+// Returns the swapped funds and any part of the input the venue didn't use
+fn swap(venue: Venue, funds: Bucket) -> (Bucket, Option<Bucket>) {
+    match venue {
+        Venue::OrderBook(trading_pair) => {
+            let (remainder, output) = borrow_component!(trading_pair)
+                .call::<(Bucket, Option<Bucket>)>("new_market_order", args![funds]);
+            (output.expect("The market order was not filled"), Some(remainder))
+        }
+        Venue::CandyDex(candy_dex, output_resource) => {
+            let input_resource = funds.resource_address();
+            assert!(input_resource != output_resource, "A CandyDex step needs different input and output resources");
+            // no minimum per step, the profit check at the end covers the whole route
+            let output = if input_resource == RADIX_TOKEN {
+                borrow_component!(candy_dex).call::<Bucket>(
+                    "buy_candy_sell_exact_xrd",
+                    args![Decimal::zero(), output_resource, funds],
+                )
+            } else if output_resource == RADIX_TOKEN {
+                borrow_component!(candy_dex).call::<Bucket>("buy_xrd_sell_exact_candy", args![Decimal::zero(), funds])
+            } else {
+                borrow_component!(candy_dex).call::<Bucket>(
+                    "buy_candy_sell_exact_candy",
+                    args![Decimal::zero(), output_resource, funds],
+                )
+            };
+            (output, None)
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use arbitrage::Venue;
+use radix_engine::ledger::*;
+use radix_engine::transaction::*;
+use scrypto::prelude::*;
+
+struct Setup {
+    pk: EcdsaPublicKey,
+    sk: EcdsaPrivateKey,
+    account: ComponentAddress,
+    candy: ResourceAddress,
+    candy_dex: ComponentAddress,
+    trading_pair: ComponentAddress,
+    arbitrage: ComponentAddress,
+}
+
+// CandyDex sells CANDY at 1 XRD while the order book has a bid for CANDY at 2 XRD
+fn setup(executor: &mut TransactionExecutor<InMemorySubstateStore>) -> Setup {
+    let (pk, sk, account) = executor.new_account();
+    let package_dir = env!("CARGO_MANIFEST_DIR");
+    let flashloan_package = executor.publish_package(compile_package!(format!("{}/../flashloan", package_dir))).unwrap();
+    let candy_dex_package = executor.publish_package(compile_package!(format!("{}/../../single-token-amm-dex", package_dir))).unwrap();
+    let order_book_package = executor.publish_package(compile_package!(format!("{}/../../order-book-dex", package_dir))).unwrap();
+    let arbitrage_package = executor.publish_package(compile_package!()).unwrap();
+
+    let mut metadata = HashMap::new();
+    metadata.insert("name".to_string(), "Candy".to_string());
+    metadata.insert("symbol".to_string(), "CANDY".to_string());
+    let transaction = TransactionBuilder::new()
+        .new_token_fixed(metadata, dec!("100000"))
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    assert!(receipt.result.is_ok());
+    let candy = receipt.new_resource_addresses[0];
+
+    // FlashLoan with 100 000 XRD at 1% interest
+    let transaction = TransactionBuilder::new()
+        .withdraw_from_account_by_amount(dec!("100000"), RADIX_TOKEN, account)
+        .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+            builder.call_function(flashloan_package, "FlashLoan", "new", args![scrypto::resource::Bucket(bucket_id), dec!("1")])
+        })
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    assert!(receipt.result.is_ok());
+    let flashloan = receipt.new_component_addresses[0];
+
+    // CandyDex without fees, stocked with 10 000 CANDY at 1 XRD
+    let transaction = TransactionBuilder::new()
+        .call_function(candy_dex_package, "CandyDex", "new", args![dec!("0")])
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    assert!(receipt.result.is_ok());
+    let candy_dex = receipt.new_component_addresses[0];
+
+    let transaction = TransactionBuilder::new()
+        .withdraw_from_account_by_amount(dec!("10000"), candy, account)
+        .take_from_worktop(candy, |builder, bucket_id| {
+            builder.call_method(candy_dex, "stock_candy", args![scrypto::resource::Bucket(bucket_id), dec!("1")])
+        })
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    assert!(receipt.result.is_ok());
+
+    // CANDY/XRD order book with a bid of 10 000 XRD at 2 XRD per CANDY
+    let transaction = TransactionBuilder::new()
+        .call_function(order_book_package, "TradingPair", "instantiate", args![candy, RADIX_TOKEN])
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    assert!(receipt.result.is_ok());
+    let trading_pair = receipt.new_component_addresses[0];
+
+    let transaction = TransactionBuilder::new()
+        .withdraw_from_account_by_amount(dec!("10000"), RADIX_TOKEN, account)
+        .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+            builder.call_method(trading_pair, "new_limit_order", args![scrypto::resource::Bucket(bucket_id), dec!("2")])
+        })
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    assert!(receipt.result.is_ok());
+
+    let transaction = TransactionBuilder::new()
+        .call_function(arbitrage_package, "ArbitrageExecutor", "new", args![flashloan])
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    assert!(receipt.result.is_ok());
+    let arbitrage = receipt.new_component_addresses[0];
+
+    Setup { pk, sk, account, candy, candy_dex, trading_pair, arbitrage }
+}
+
+fn execute(
+    executor: &mut TransactionExecutor<InMemorySubstateStore>,
+    setup: &Setup,
+    route: Vec<Venue>,
+    minimum_profit: Decimal,
+) -> Receipt {
+    let transaction = TransactionBuilder::new()
+        .call_method(setup.arbitrage, "execute", args![dec!("1000"), route, minimum_profit])
+        .call_method_with_all_resources(setup.account, "deposit_batch")
+        .build(executor.get_nonce([setup.pk]))
+        .sign([&setup.sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    println!("{:?}\n", receipt);
+    receipt
+}
+
+fn xrd_balance(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Setup) -> Decimal {
+    let transaction = TransactionBuilder::new()
+        .call_method(setup.account, "balance", args![RADIX_TOKEN])
+        .build(executor.get_nonce([setup.pk]))
+        .sign([&setup.sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    assert!(receipt.result.is_ok());
+    scrypto_decode(&receipt.outputs[0].raw[..]).unwrap()
+}
+
+#[test]
+fn test_profitable_route() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup(&mut executor);
+    let balance_before = xrd_balance(&mut executor, &setup);
+
+    // 1000 XRD buy 900 CANDY on CandyDex (the price moves to 1.11), which sell for 1800 XRD on the order book.
+    // 1010 XRD repay the loan, so 790 XRD are left
+    let route = vec![Venue::CandyDex(setup.candy_dex, setup.candy), Venue::OrderBook(setup.trading_pair)];
+    let receipt = execute(&mut executor, &setup, route, dec!("700"));
+    assert!(receipt.result.is_ok());
+
+    // within rounding of the CandyDex price
+    let profit = xrd_balance(&mut executor, &setup) - balance_before;
+    assert!(profit > dec!("789.99") && profit < dec!("790.01"), "Unexpected profit {}", profit);
+}
+
+#[test]
+fn test_reverts_below_minimum_profit() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup(&mut executor);
+
+    let route = vec![Venue::CandyDex(setup.candy_dex, setup.candy), Venue::OrderBook(setup.trading_pair)];
+    let receipt = execute(&mut executor, &setup, route, dec!("800"));
+    assert!(!receipt.result.is_ok());
+}
+
+#[test]
+fn test_reverts_when_route_does_not_return_to_loaned_resource() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup(&mut executor);
+
+    let route = vec![Venue::CandyDex(setup.candy_dex, setup.candy)];
+    let receipt = execute(&mut executor, &setup, route, dec!("0"));
+    assert!(!receipt.result.is_ok());
+
+    // the failed route left the venues untouched, so the profitable one still makes the same profit
+    let balance_before = xrd_balance(&mut executor, &setup);
+    let route = vec![Venue::CandyDex(setup.candy_dex, setup.candy), Venue::OrderBook(setup.trading_pair)];
+    let receipt = execute(&mut executor, &setup, route, dec!("0"));
+    assert!(receipt.result.is_ok());
+    let profit = xrd_balance(&mut executor, &setup) - balance_before;
+    assert!(profit > dec!("789.99") && profit < dec!("790.01"), "Unexpected profit {}", profit);
+}
@@ -1,9 +1,22 @@
+mod pool;
+
 use scrypto::prelude::*;
 
+// Data of the loan receipt returned by `take_loan`, it can only be burned by repaying the loan
+#[derive(NonFungibleData)]
+pub struct LoanDue {
+    amount: Decimal,
+    amount_due: Decimal,
+}
+
 blueprint! {
     struct FlashLoan {
         interest: Decimal,
-        vault: Vault
+        vault: Vault,
+        // Used to mint and burn loan receipts
+        auth_vault: Vault,
+        // Transient resource, it cannot be deposited anywhere so it has to be burned in the same transaction
+        loan_receipt: ResourceAddress
     }
 
     impl FlashLoan {
@@ -11,9 +24,23 @@ blueprint! {
          * Generate a FlashLoan component with a specified interest and a specified bucket as funds
          */
         pub fn new(tokens: Bucket, interest: Decimal) -> ComponentAddress {
+            let auth_token = ResourceBuilder::new_fungible()
+                .divisibility(DIVISIBILITY_NONE)
+                .metadata("name", "FlashLoan Auth")
+                .initial_supply(1);
+
+            let loan_receipt = ResourceBuilder::new_non_fungible()
+                .metadata("name", "Flash Loan Receipt")
+                .mintable(rule!(require(auth_token.resource_address())), LOCKED)
+                .burnable(rule!(require(auth_token.resource_address())), LOCKED)
+                .restrict_deposit(rule!(deny_all), LOCKED)
+                .no_initial_supply();
+
             Self {
                 vault: Vault::with_bucket(tokens),
                 interest: interest,
+                auth_vault: Vault::with_bucket(auth_token),
+                loan_receipt: loan_receipt
             }
             .instantiate()
             .globalize()
@@ -27,7 +54,7 @@ blueprint! {
             let mut returned_bucket: Bucket = borrow_component!(component_address).call::<Bucket>("execute", args).into();
 
             // Make sure they repaid in loan in full
-            let amount_to_take = amount * ((self.interest / 100) + 1);
+            let amount_to_take = self.amount_due(amount);
             assert!(returned_bucket.amount() >= amount_to_take, "You have to return more than {}", amount_to_take);
 
             self.vault.put(returned_bucket.take(amount_to_take));
@@ -35,5 +62,48 @@ blueprint! {
             // Return the change back to the component
             return returned_bucket;
         }
+
+        /*
+         * Take a loan without a callback, the returned receipt cannot be deposited so the transaction
+         * only succeeds if the receipt is given back to `repay_loan` together with the amount due
+         */
+        pub fn take_loan(&mut self, amount: Decimal) -> (Bucket, Bucket) {
+            assert!(amount < self.vault.amount(), "Not enough funds to loan");
+
+            let amount_due = self.amount_due(amount);
+            let loan_receipt = self.auth_vault.authorize(|| {
+                borrow_resource_manager!(self.loan_receipt).mint_non_fungible(
+                    &NonFungibleId::random(),
+                    LoanDue {
+                        amount: amount,
+                        amount_due: amount_due
+                    }
+                )
+            });
+
+            (self.vault.take(amount), loan_receipt)
+        }
+
+        pub fn repay_loan(&mut self, mut repayment: Bucket, loan_receipt: Bucket) -> Bucket {
+            assert!(loan_receipt.resource_address() == self.loan_receipt, "Invalid loan receipt");
+            assert!(repayment.resource_address() == self.vault.resource_address(), "You have to repay with the loaned resource");
+
+            // Make sure they repaid in loan in full
+            let loan_data: LoanDue = loan_receipt.non_fungible::<LoanDue>().data();
+            assert!(repayment.amount() >= loan_data.amount_due, "You have to return more than {}", loan_data.amount_due);
+            info!("Repaying loan of {} with {}", loan_data.amount, loan_data.amount_due);
+
+            self.vault.put(repayment.take(loan_data.amount_due));
+            self.auth_vault.authorize(|| {
+                loan_receipt.burn();
+            });
+
+            // Return the change
+            repayment
+        }
+
+        fn amount_due(&self, amount: Decimal) -> Decimal {
+            amount * ((self.interest / 100) + 1)
+        }
     }
 }
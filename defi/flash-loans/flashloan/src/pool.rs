@@ -0,0 +1,234 @@
+use scrypto::prelude::*;
+
+// Data of the loan receipt returned by `take_loan`, it can only be burned by repaying the loan
+#[derive(NonFungibleData)]
+pub struct PoolLoanDue {
+    resource: ResourceAddress,
+    amount: Decimal,
+    amount_due: Decimal,
+}
+
+// Everything the pool tracks about one of its resources
+#[derive(TypeId, Encode, Decode, Describe)]
+pub struct AssetStats {
+    pub resource: ResourceAddress,
+    pub share_resource: ResourceAddress,
+    // available to borrow right now
+    pub liquidity: Decimal,
+    // on loan right now (only ever non zero within the transaction that took the loan)
+    pub outstanding: Decimal,
+    // outstanding / (liquidity + outstanding) in percent
+    pub utilisation_percent: Decimal,
+    pub total_borrowed: Decimal,
+    pub total_fees_earned: Decimal,
+}
+
+blueprint! {
+    struct FlashLoanPool {
+        interest: Decimal,
+        // Used to mint and burn share tokens and loan receipts
+        auth_vault: Vault,
+        // Transient resource, it cannot be deposited anywhere so it has to be burned in the same transaction
+        loan_receipt: ResourceAddress,
+        // One vault per loanable resource
+        vaults: HashMap<ResourceAddress, Vault>,
+        // One share token per loanable resource, shares are a claim on a fraction of that resource's vault
+        share_resources: HashMap<ResourceAddress, ResourceAddress>,
+        outstanding: HashMap<ResourceAddress, Decimal>,
+        total_borrowed: HashMap<ResourceAddress, Decimal>,
+        total_fees_earned: HashMap<ResourceAddress, Decimal>
+    }
+
+    impl FlashLoanPool {
+        /*
+         * Generate a FlashLoanPool component with a specified interest, liquidity providers add the resources
+         */
+        pub fn new(interest: Decimal) -> ComponentAddress {
+            assert!(!interest.is_negative(), "Interest cannot be negative");
+
+            let auth_token = ResourceBuilder::new_fungible()
+                .divisibility(DIVISIBILITY_NONE)
+                .metadata("name", "FlashLoanPool Auth")
+                .initial_supply(1);
+
+            let loan_receipt = ResourceBuilder::new_non_fungible()
+                .metadata("name", "Flash Loan Pool Receipt")
+                .mintable(rule!(require(auth_token.resource_address())), LOCKED)
+                .burnable(rule!(require(auth_token.resource_address())), LOCKED)
+                .restrict_deposit(rule!(deny_all), LOCKED)
+                .no_initial_supply();
+
+            Self {
+                interest: interest,
+                auth_vault: Vault::with_bucket(auth_token),
+                loan_receipt: loan_receipt,
+                vaults: HashMap::new(),
+                share_resources: HashMap::new(),
+                outstanding: HashMap::new(),
+                total_borrowed: HashMap::new(),
+                total_fees_earned: HashMap::new()
+            }
+            .instantiate()
+            .globalize()
+        }
+
+        /*
+         * Add liquidity for any fungible resource, the first deposit of a resource makes it loanable.
+         * Returns share tokens for that resource
+         */
+        pub fn add_liquidity(&mut self, tokens: Bucket) -> Bucket {
+            let resource = tokens.resource_address();
+            assert!(!tokens.is_empty(), "You cannot add zero liquidity");
+            assert_is_fungible(&resource);
+
+            if !self.vaults.contains_key(&resource) {
+                self.add_asset(resource);
+            }
+            self.assert_no_outstanding_loan(&resource);
+
+            let share_manager = borrow_resource_manager!(self.share_resources[&resource]);
+            let vault = self.vaults.get_mut(&resource).unwrap();
+
+            // the first provider (or the first after everyone left) sets the share price to 1
+            let shares = if share_manager.total_supply().is_zero() || vault.amount().is_zero() {
+                tokens.amount()
+            } else {
+                tokens.amount() * share_manager.total_supply() / vault.amount()
+            };
+            vault.put(tokens);
+
+            self.auth_vault.authorize(|| share_manager.mint(shares))
+        }
+
+        /*
+         * Burn share tokens for their part of the resource, including the interest earned meanwhile
+         */
+        pub fn remove_liquidity(&mut self, shares: Bucket) -> Bucket {
+            let resource = self.get_resource_for_shares(shares.resource_address());
+            self.assert_no_outstanding_loan(&resource);
+
+            let share_manager = borrow_resource_manager!(shares.resource_address());
+            let vault = self.vaults.get_mut(&resource).unwrap();
+            let amount = shares.amount() * vault.amount() / share_manager.total_supply();
+
+            self.auth_vault.authorize(|| {
+                shares.burn();
+            });
+
+            vault.take(amount)
+        }
+
+        /*
+         * Take a loan of any resource in the pool, the returned receipt cannot be deposited so the transaction
+         * only succeeds if the receipt is given back to `repay_loan` together with the amount due
+         */
+        pub fn take_loan(&mut self, resource: ResourceAddress, amount: Decimal) -> (Bucket, Bucket) {
+            let vault = self.vaults.get_mut(&resource).expect("This resource cannot be loaned from the pool");
+            assert!(amount < vault.amount(), "Not enough funds to loan");
+            let loan = vault.take(amount);
+
+            *self.outstanding.get_mut(&resource).unwrap() += amount;
+            *self.total_borrowed.get_mut(&resource).unwrap() += amount;
+
+            let loan_data = PoolLoanDue {
+                resource: resource,
+                amount: amount,
+                amount_due: amount * ((self.interest / 100) + 1)
+            };
+            let loan_receipt = self.auth_vault.authorize(|| {
+                borrow_resource_manager!(self.loan_receipt).mint_non_fungible(&NonFungibleId::random(), loan_data)
+            });
+
+            (loan, loan_receipt)
+        }
+
+        pub fn repay_loan(&mut self, mut repayment: Bucket, loan_receipt: Bucket) -> Bucket {
+            assert!(loan_receipt.resource_address() == self.loan_receipt, "Invalid loan receipt");
+
+            let loan_data: PoolLoanDue = loan_receipt.non_fungible::<PoolLoanDue>().data();
+            assert!(repayment.resource_address() == loan_data.resource, "You have to repay with the loaned resource");
+            assert!(repayment.amount() >= loan_data.amount_due, "You have to return more than {}", loan_data.amount_due);
+
+            // the interest stays in the vault, so every share of this resource is worth proportionally more
+            self.vaults.get_mut(&loan_data.resource).unwrap().put(repayment.take(loan_data.amount_due));
+            *self.outstanding.get_mut(&loan_data.resource).unwrap() -= loan_data.amount;
+            *self.total_fees_earned.get_mut(&loan_data.resource).unwrap() += loan_data.amount_due - loan_data.amount;
+
+            self.auth_vault.authorize(|| {
+                loan_receipt.burn();
+            });
+
+            // Return the change
+            repayment
+        }
+
+        pub fn get_assets(&self) -> Vec<ResourceAddress> {
+            self.vaults.keys().cloned().collect()
+        }
+
+        pub fn get_asset_stats(&self, resource: ResourceAddress) -> AssetStats {
+            let liquidity = self.vaults.get(&resource).expect("This resource is not in the pool").amount();
+            let outstanding = self.outstanding[&resource];
+            let utilisation_percent = if (liquidity + outstanding).is_zero() {
+                Decimal::zero()
+            } else {
+                outstanding * 100 / (liquidity + outstanding)
+            };
+
+            AssetStats {
+                resource: resource,
+                share_resource: self.share_resources[&resource],
+                liquidity: liquidity,
+                outstanding: outstanding,
+                utilisation_percent: utilisation_percent,
+                total_borrowed: self.total_borrowed[&resource],
+                total_fees_earned: self.total_fees_earned[&resource]
+            }
+        }
+
+        fn add_asset(&mut self, resource: ResourceAddress) {
+            let symbol = borrow_resource_manager!(resource)
+                .metadata()
+                .get("symbol")
+                .cloned()
+                .unwrap_or(resource.to_string());
+
+            let share_resource = ResourceBuilder::new_fungible()
+                .divisibility(DIVISIBILITY_MAXIMUM)
+                .metadata("name", format!("Flash Loan Pool Share ({})", symbol))
+                .metadata("underlying", resource.to_string())
+                .mintable(rule!(require(self.auth_vault.resource_address())), LOCKED)
+                .burnable(rule!(require(self.auth_vault.resource_address())), LOCKED)
+                .no_initial_supply();
+
+            self.vaults.insert(resource, Vault::new(resource));
+            self.share_resources.insert(resource, share_resource);
+            self.outstanding.insert(resource, Decimal::zero());
+            self.total_borrowed.insert(resource, Decimal::zero());
+            self.total_fees_earned.insert(resource, Decimal::zero());
+        }
+
+        fn get_resource_for_shares(&self, share_resource: ResourceAddress) -> ResourceAddress {
+            *self.share_resources
+                .iter()
+                .find(|(_, shares)| **shares == share_resource)
+                .expect("Invalid share tokens")
+                .0
+        }
+
+        // providers can't enter or leave in the middle of a loan, the vault doesn't hold the full value then
+        fn assert_no_outstanding_loan(&self, resource: &ResourceAddress) {
+            assert!(self.outstanding[resource].is_zero(), "This resource is currently on loan");
+        }
+    }
+}
+
+fn assert_is_fungible(resource: &ResourceAddress) {
+    match borrow_resource_manager!(*resource).resource_type() {
+        ResourceType::Fungible { .. } => (), // OK
+        ResourceType::NonFungible => panic!(
+            "Invalid resource: resource {} is non fungible and cannot be loaned",
+            resource
+        ),
+    }
+}
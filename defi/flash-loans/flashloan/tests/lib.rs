@@ -0,0 +1,247 @@
+use radix_engine::ledger::*;
+use radix_engine::transaction::*;
+use scrypto::prelude::*;
+
+struct Account {
+    pk: EcdsaPublicKey,
+    sk: EcdsaPrivateKey,
+    address: ComponentAddress,
+}
+
+struct Setup {
+    account: Account,
+    flashloan: ComponentAddress,
+    loan_receipt: ResourceAddress,
+}
+
+fn execute(executor: &mut TransactionExecutor<InMemorySubstateStore>, account: &Account, builder: &mut TransactionBuilder) -> Receipt {
+    let transaction = builder
+        .call_method_with_all_resources(account.address, "deposit_batch")
+        .build(executor.get_nonce([account.pk]))
+        .sign([&account.sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    println!("{:?}\n", receipt);
+    receipt
+}
+
+fn run(executor: &mut TransactionExecutor<InMemorySubstateStore>, account: &Account, builder: &mut TransactionBuilder) -> Receipt {
+    let receipt = execute(executor, account, builder);
+    assert!(receipt.result.is_ok());
+    receipt
+}
+
+fn balance(executor: &mut TransactionExecutor<InMemorySubstateStore>, account: &Account, resource: ResourceAddress) -> Decimal {
+    let receipt = run(executor, account, TransactionBuilder::new().call_method(account.address, "balance", args![resource]));
+    scrypto_decode(&receipt.outputs[0].raw[..]).unwrap()
+}
+
+// FlashLoan with 10 000 XRD at 5% interest
+fn setup(executor: &mut TransactionExecutor<InMemorySubstateStore>) -> Setup {
+    let (pk, sk, address) = executor.new_account();
+    let account = Account { pk, sk, address };
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let receipt = run(
+        executor,
+        &account,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(dec!("10000"), RADIX_TOKEN, account.address)
+            .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+                builder.call_function(package, "FlashLoan", "new", args![scrypto::resource::Bucket(bucket_id), dec!("5")])
+            }),
+    );
+    let flashloan = receipt.new_component_addresses[0];
+    // auth token and loan receipt resources
+    let loan_receipt = receipt.new_resource_addresses[1];
+
+    Setup { account, flashloan, loan_receipt }
+}
+
+// Take a loan of `borrowed` XRD and give `repaid` XRD back with the receipt, topped up from the account
+fn take_and_repay(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Setup, borrowed: Decimal, repaid: Decimal) -> Receipt {
+    execute(
+        executor,
+        &setup.account,
+        TransactionBuilder::new()
+            .call_method(setup.flashloan, "take_loan", args![borrowed])
+            .withdraw_from_account_by_amount(dec!("100"), RADIX_TOKEN, setup.account.address)
+            .take_from_worktop_by_amount(repaid, RADIX_TOKEN, |builder, repayment_id| {
+                builder.take_from_worktop(setup.loan_receipt, |builder, receipt_id| {
+                    builder.call_method(
+                        setup.flashloan,
+                        "repay_loan",
+                        args![scrypto::resource::Bucket(repayment_id), scrypto::resource::Bucket(receipt_id)],
+                    )
+                })
+            }),
+    )
+}
+
+#[test]
+fn test_repaid_loan_succeeds() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup(&mut executor);
+
+    let before = balance(&mut executor, &setup.account, RADIX_TOKEN);
+    assert!(take_and_repay(&mut executor, &setup, dec!("1000"), dec!("1050")).result.is_ok());
+
+    // only the interest is paid, the change of the top up comes back and the receipt is burned
+    assert_eq!(before - balance(&mut executor, &setup.account, RADIX_TOKEN), dec!("50"));
+}
+
+#[test]
+fn test_under_repaid_loan_aborts() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup(&mut executor);
+
+    let before = balance(&mut executor, &setup.account, RADIX_TOKEN);
+    assert!(take_and_repay(&mut executor, &setup, dec!("1000"), dec!("1049")).result.is_err());
+    assert_eq!(balance(&mut executor, &setup.account, RADIX_TOKEN), before);
+}
+
+#[test]
+fn test_unreturned_loan_receipt_aborts() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup(&mut executor);
+
+    // the loan and the receipt end up in deposit_batch, which can't take the receipt
+    let before = balance(&mut executor, &setup.account, RADIX_TOKEN);
+    let receipt = execute(
+        &mut executor,
+        &setup.account,
+        TransactionBuilder::new().call_method(setup.flashloan, "take_loan", args![dec!("1000")]),
+    );
+    assert!(receipt.result.is_err());
+    assert_eq!(balance(&mut executor, &setup.account, RADIX_TOKEN), before);
+}
+
+struct PoolSetup {
+    account: Account,
+    pool: ComponentAddress,
+    loan_receipt: ResourceAddress,
+    share_resource: ResourceAddress,
+}
+
+// FlashLoanPool at 1% interest with 1000 XRD of liquidity from the account
+fn setup_pool(executor: &mut TransactionExecutor<InMemorySubstateStore>) -> PoolSetup {
+    let (pk, sk, address) = executor.new_account();
+    let account = Account { pk, sk, address };
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let receipt = run(executor, &account, TransactionBuilder::new().call_function(package, "FlashLoanPool", "new", args![dec!("1")]));
+    let pool = receipt.new_component_addresses[0];
+    // auth token and loan receipt resources
+    let loan_receipt = receipt.new_resource_addresses[1];
+
+    let receipt = add_liquidity(executor, &account, pool, dec!("1000"));
+    assert!(receipt.result.is_ok());
+    let share_resource = receipt.new_resource_addresses[0];
+
+    PoolSetup { account, pool, loan_receipt, share_resource }
+}
+
+fn add_liquidity(executor: &mut TransactionExecutor<InMemorySubstateStore>, account: &Account, pool: ComponentAddress, amount: Decimal) -> Receipt {
+    execute(
+        executor,
+        account,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(amount, RADIX_TOKEN, account.address)
+            .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+                builder.call_method(pool, "add_liquidity", args![scrypto::resource::Bucket(bucket_id)])
+            }),
+    )
+}
+
+fn remove_liquidity(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &PoolSetup, shares: Decimal) -> Receipt {
+    execute(
+        executor,
+        &setup.account,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(shares, setup.share_resource, setup.account.address)
+            .take_from_worktop(setup.share_resource, |builder, bucket_id| {
+                builder.call_method(setup.pool, "remove_liquidity", args![scrypto::resource::Bucket(bucket_id)])
+            }),
+    )
+}
+
+#[test]
+fn test_pool_shares_earn_the_interest() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup_pool(&mut executor);
+    assert_eq!(balance(&mut executor, &setup.account, setup.share_resource), dec!("1000"));
+
+    // a loan of 500 XRD leaves 5 XRD of interest in the pool
+    run(
+        &mut executor,
+        &setup.account,
+        TransactionBuilder::new()
+            .call_method(setup.pool, "take_loan", args![RADIX_TOKEN, dec!("500")])
+            .withdraw_from_account_by_amount(dec!("5"), RADIX_TOKEN, setup.account.address)
+            .take_from_worktop_by_amount(dec!("505"), RADIX_TOKEN, |builder, repayment_id| {
+                builder.take_from_worktop(setup.loan_receipt, |builder, receipt_id| {
+                    builder.call_method(
+                        setup.pool,
+                        "repay_loan",
+                        args![scrypto::resource::Bucket(repayment_id), scrypto::resource::Bucket(receipt_id)],
+                    )
+                })
+            }),
+    );
+
+    // a share is now worth 1.005 XRD, for new and old providers alike
+    assert!(add_liquidity(&mut executor, &setup.account, setup.pool, dec!("1005")).result.is_ok());
+    assert_eq!(balance(&mut executor, &setup.account, setup.share_resource), dec!("2000"));
+
+    let before = balance(&mut executor, &setup.account, RADIX_TOKEN);
+    assert!(remove_liquidity(&mut executor, &setup, dec!("1000")).result.is_ok());
+    assert_eq!(balance(&mut executor, &setup.account, RADIX_TOKEN) - before, dec!("1005"));
+    assert_eq!(balance(&mut executor, &setup.account, setup.share_resource), dec!("1000"));
+}
+
+// Take a loan of 500 XRD, call add_liquidity or remove_liquidity with 100 of a resource while it is outstanding, then repay it
+fn change_liquidity_during_loan(
+    executor: &mut TransactionExecutor<InMemorySubstateStore>,
+    setup: &PoolSetup,
+    resource: ResourceAddress,
+    method: &str,
+) -> Receipt {
+    execute(
+        executor,
+        &setup.account,
+        TransactionBuilder::new()
+            .call_method(setup.pool, "take_loan", args![RADIX_TOKEN, dec!("500")])
+            .withdraw_from_account_by_amount(dec!("100"), resource, setup.account.address)
+            .take_from_worktop_by_amount(dec!("100"), resource, |builder, bucket_id| {
+                builder.call_method(setup.pool, method, args![scrypto::resource::Bucket(bucket_id)])
+            })
+            .withdraw_from_account_by_amount(dec!("5"), RADIX_TOKEN, setup.account.address)
+            .take_from_worktop_by_amount(dec!("505"), RADIX_TOKEN, |builder, repayment_id| {
+                builder.take_from_worktop(setup.loan_receipt, |builder, receipt_id| {
+                    builder.call_method(
+                        setup.pool,
+                        "repay_loan",
+                        args![scrypto::resource::Bucket(repayment_id), scrypto::resource::Bucket(receipt_id)],
+                    )
+                })
+            }),
+    )
+}
+
+#[test]
+fn test_pool_liquidity_cannot_change_during_a_loan() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup_pool(&mut executor);
+
+    // the loan is repaid in both transactions, only the liquidity change makes them fail
+    assert!(change_liquidity_during_loan(&mut executor, &setup, RADIX_TOKEN, "add_liquidity").result.is_err());
+    assert!(change_liquidity_during_loan(&mut executor, &setup, setup.share_resource, "remove_liquidity").result.is_err());
+
+    // nothing changed, the same removal without a loan goes through
+    assert_eq!(balance(&mut executor, &setup.account, setup.share_resource), dec!("1000"));
+    assert!(remove_liquidity(&mut executor, &setup, dec!("100")).result.is_ok());
+}
@@ -12,19 +12,28 @@ export package=$(resim publish . | sed -nr "s/Success! New Package: ([[:alnum:]_
 
 CP_OP=$(resim call-function $package Insurance new 100,$xrd)
 export component=$(echo "$CP_OP" | sed -nr "s/└─ Component: ([[:alnum:]_]+)/\1/p")
-export fundraiser_badge=$(echo "$CP_OP" | sed -nr "s/.*Resource: ([[:alnum:]_]+)/\1/p" | sed '1!d')
-export patron_mint_badge=$(echo "$CP_OP" | sed -nr "s/.*Resource: ([[:alnum:]_]+)/\1/p" | sed '2!d')
-
-CM_OP=$(resim call-method $component make_policy "property" 10 5 100 3)
-export policy1=$(echo "$CM_OP" | sed -nr "s/.*Resource: ([[:alnum:]_]+)/\1/p" | sed '1!d')
-resim call-method $component purchase $policy1 $acc1 5,$xrd
-resim call-method $component approve $acc1 $policy1 10
+export org_badge=$(echo "$CP_OP" | sed -nr "s/.*Resource: ([[:alnum:]_]+)/\1/p" | sed '1!d')
+export purchase_resource=$(echo "$CP_OP" | sed -nr "s/.*Resource: ([[:alnum:]_]+)/\1/p" | sed '3!d')
+export claim_resource=$(echo "$CP_OP" | sed -nr "s/.*Resource: ([[:alnum:]_]+)/\1/p" | sed '4!d')
+
+resim call-method $component make_policy "property" 10 5 100 3 --proofs 1,$org_badge
+resim call-method $component purchase 0 $acc1 5,$xrd
+resim call-method $component get_active_coverage $acc1
+resim call-method $component approve "#0000000000000000" 5 --proofs 1,$org_badge
+
+resim call-method $component submit_claim 1,$purchase_resource 3 "roof damage"
+resim call-method $component approve_claim "#0000000000000000" 2 --proofs 1,$org_badge
+resim call-method $component dispute_claim 1,$claim_resource
+resim call-method $component approve_claim "#0000000000000000" 3 --proofs 1,$org_badge
+resim set-current-epoch 11
+resim call-method $component settle_claim "#0000000000000000"
+resim call-method $component get_claim "#0000000000000000"
 
 resim set-current-epoch 101
-resim call-method $component burn_purchases $acc1 $policy1
-resim call-method $component burn_policies $policy1
+resim call-method $component burn_purchase "#0000000000000000" --proofs 1,$org_badge
+resim call-method $component burn_policies 0 --proofs 1,$org_badge
 
-resim call-method $component deposit 100,$xrd
-resim call-method $component withdraw 50
+resim call-method $component deposit 100,$xrd --proofs 1,$org_badge
+resim call-method $component withdraw 50 --proofs 1,$org_badge
 resim call-method $component assets
 resim call-method $component locked
\ No newline at end of file
@@ -1,5 +1,120 @@
+use sbor::*;
 use scrypto::prelude::*;
 
+mod mock_oracle;
+
+/// Epochs after a decision on a claim during which it can be disputed, used by `new`
+pub const DEFAULT_DISPUTE_EPOCHS: u64 = 10;
+
+/// Condition a parametric policy pays out on, checked on the feed of an oracle
+#[derive(TypeId, Encode, Decode, Describe, Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// The value of the feed was reported below the threshold
+    Below(String, Decimal),
+    /// The value of the feed was reported above the threshold
+    Above(String, Decimal),
+    /// The event of the feed was reported
+    Event(String),
+}
+
+/// Oracle and condition of a parametric policy.
+/// The oracle must provide `get_values(feed) -> Vec<(Decimal, u64)>` and `get_flags(feed) -> Vec<u64>`, every report with its epoch
+#[derive(TypeId, Encode, Decode, Describe, Debug, Clone, PartialEq)]
+pub struct Trigger {
+    pub oracle: ComponentAddress,
+    pub condition: Condition,
+}
+
+impl Trigger {
+    /// First epoch after `start` and up to `end` the oracle reported the condition as met, None if it didn't.
+    /// Every report counts, a breach stays claimable when a later report reverts it
+    fn reported_epoch(&self, start: u64, end: u64) -> Option<u64> {
+        let oracle = borrow_component!(self.oracle);
+        let epochs: Vec<u64> = match &self.condition {
+            Condition::Below(feed, threshold) => oracle
+                .call::<Vec<(Decimal, u64)>>("get_values", args![feed.clone()])
+                .into_iter()
+                .filter(|(value, _)| value < threshold)
+                .map(|(_, epoch)| epoch)
+                .collect(),
+            Condition::Above(feed, threshold) => oracle
+                .call::<Vec<(Decimal, u64)>>("get_values", args![feed.clone()])
+                .into_iter()
+                .filter(|(value, _)| value > threshold)
+                .map(|(_, epoch)| epoch)
+                .collect(),
+            Condition::Event(feed) => oracle.call::<Vec<u64>>("get_flags", args![feed.clone()]),
+        };
+        epochs.into_iter().filter(|epoch| *epoch > start && *epoch <= end).min()
+    }
+}
+
+/// Policy template, one NFT per policy kept by the component
+#[derive(NonFungibleData)]
+pub struct Policy {
+    policy_type: String,
+    /// Amount of XRD covered by each purchase
+    coverage: Decimal,
+    /// Amount of XRD paid for each purchase
+    price: Decimal,
+    /// Epochs a purchase is valid for
+    duration: u64,
+    /// Oracle condition of a parametric policy, its purchases pay out automatically once it is met
+    trigger: Option<Trigger>,
+    /// Purchases left, their coverage is locked
+    #[scrypto(mutable)]
+    available: u64,
+}
+
+/// Purchase NFT, given to the buyer of a policy
+#[derive(NonFungibleData)]
+pub struct Purchase {
+    policy_id: u64,
+    /// Account receiving the payouts
+    insurer: ComponentAddress,
+    coverage: Decimal,
+    /// Epoch of the purchase, the coverage starts after it
+    purchased_epoch: u64,
+    /// Last epoch of the coverage
+    expires: u64,
+    /// Trigger of the policy, for parametric policies
+    trigger: Option<Trigger>,
+    /// Coverage not paid out or reserved by open claims yet
+    #[scrypto(mutable)]
+    remaining_coverage: Decimal,
+    /// The remaining coverage was released after the expiry
+    #[scrypto(mutable)]
+    released: bool,
+}
+
+#[derive(TypeId, Encode, Decode, Describe, Debug, Clone, PartialEq)]
+pub enum ClaimStatus {
+    /// Waiting for the org
+    Submitted,
+    /// The org approved this amount, the whole claim or a part of it
+    Approved(Decimal),
+    Rejected,
+    /// The holder disputed the decision, waiting for the org to decide again
+    Disputed,
+    /// Paid out after the dispute window, 0 for a rejected claim
+    Settled(Decimal),
+}
+
+/// Claim NFT, given to the holder of the purchase NFT submitting the claim. Payouts go to the insurer of the purchase
+#[derive(NonFungibleData)]
+pub struct Claim {
+    purchase_id: NonFungibleId,
+    amount: Decimal,
+    description: String,
+    #[scrypto(mutable)]
+    status: ClaimStatus,
+    /// Epoch of the last decision, the dispute window starts there
+    #[scrypto(mutable)]
+    decided_epoch: u64,
+    /// A decision can only be disputed once
+    #[scrypto(mutable)]
+    disputed: bool,
+}
 
 blueprint! {
     struct Insurance {
@@ -11,20 +126,42 @@ blueprint! {
         assets_pool: Vault,
         // Locked assets by policies
         locked_pool: Vault,
-       
-        // HashMap of policy address and details
-        policies: HashMap<ResourceAddress, Vault>,
-        // HashMap of insurer and policy vaults HashMap
-        purchases: HashMap<ComponentAddress, HashMap<ResourceAddress, Vault>>,
-
-        // A vector which we use to store all of the dead vaults
-        dead_vaults: Vec<Vault>
+
+        // Shares of the assets, both pools. Premiums make them worth more and payouts less
+        share_resource: ResourceAddress,
+        // Shares of the org, for the base assets and its deposits
+        org_shares: Vault,
+
+        // Policy NFTs, kept in `policies`
+        policy_resource: ResourceAddress,
+        policies: Vault,
+        // Id of the next policy
+        policy_count: u64,
+
+        // Purchase NFTs, held by the buyers
+        purchase_resource: ResourceAddress,
+        // Id of the next purchase
+        purchase_count: u64,
+        // Purchase ids of each insurer
+        insurer_purchases: HashMap<ComponentAddress, Vec<NonFungibleId>>,
+
+        // Claim NFTs
+        claim_resource: ResourceAddress,
+        // Id of the next claim
+        claim_count: u64,
+        // Epochs after a decision during which it can be disputed, the claim is paid after that
+        dispute_epochs: u64
     }
 
     impl Insurance {
-        
+
         // Create new Insurance component
         pub fn new(base_assets: Bucket) -> (ComponentAddress, Bucket) {
+            Self::new_with_dispute_window(base_assets, DEFAULT_DISPUTE_EPOCHS)
+        }
+
+        // Same as `new`, with the number of epochs during which decisions on claims can be disputed
+        pub fn new_with_dispute_window(base_assets: Bucket, dispute_epochs: u64) -> (ComponentAddress, Bucket) {
             assert!(base_assets.amount() > Decimal::zero(), "Base assets cannot be zero");
             assert!(base_assets.resource_address() == RADIX_TOKEN.into(), "You must use Radix (XRD).");
 
@@ -37,214 +174,533 @@ blueprint! {
             let org_resource_address = org_bucket.resource_address();
             let org_return_bucket: Bucket = org_bucket.take(1); // Return this badge to the caller
 
+            // Policy, purchase and claim NFTs
+            let policy_resource = ResourceBuilder::new_non_fungible()
+                .metadata("name", "Insurance Policy badge")
+                .mintable(rule!(require(org_resource_address)), LOCKED)
+                .burnable(rule!(require(org_resource_address)), LOCKED)
+                .updateable_non_fungible_data(rule!(require(org_resource_address)), LOCKED)
+                .no_initial_supply();
+
+            let purchase_resource = ResourceBuilder::new_non_fungible()
+                .metadata("name", "Insurance Purchase Badge")
+                .metadata("symbol", "IPB")
+                .mintable(rule!(require(org_resource_address)), LOCKED)
+                .updateable_non_fungible_data(rule!(require(org_resource_address)), LOCKED)
+                .no_initial_supply();
+
+            let claim_resource = ResourceBuilder::new_non_fungible()
+                .metadata("name", "Insurance Claim")
+                .mintable(rule!(require(org_resource_address)), LOCKED)
+                .updateable_non_fungible_data(rule!(require(org_resource_address)), LOCKED)
+                .no_initial_supply();
+
+            // Underwriter shares, the org gets one per base asset
+            let share_resource = ResourceBuilder::new_fungible()
+                .divisibility(DIVISIBILITY_MAXIMUM)
+                .metadata("name", "Insurance Underwriter Share")
+                .metadata("symbol", "IUS")
+                .mintable(rule!(require(org_resource_address)), LOCKED)
+                .burnable(rule!(require(org_resource_address)), LOCKED)
+                .no_initial_supply();
+            let org_shares = org_bucket.authorize(|| {
+                borrow_resource_manager!(share_resource).mint(base_assets.amount())
+            });
+
+            let access_rules = AccessRules::new()
+                .method("make_policy", rule!(require(org_resource_address)))
+                .method("make_parametric_policy", rule!(require(org_resource_address)))
+                .method("approve", rule!(require(org_resource_address)))
+                .method("burn_purchase", rule!(require(org_resource_address)))
+                .method("burn_policies", rule!(require(org_resource_address)))
+                .method("deposit", rule!(require(org_resource_address)))
+                .method("withdraw", rule!(require(org_resource_address)))
+                .method("approve_claim", rule!(require(org_resource_address)))
+                .method("reject_claim", rule!(require(org_resource_address)))
+                .default(rule!(allow_all));
+
             let assets_address = base_assets.resource_address();
             let component = Self {
                 org_vault: Vault::with_bucket(org_bucket),
                 org_badge: org_resource_address,
                 assets_pool: Vault::with_bucket(base_assets),
                 locked_pool: Vault::new(assets_address),
-                policies:HashMap::new(),
-                purchases: HashMap::new(),
-                dead_vaults: Vec::new(),
+                share_resource: share_resource,
+                org_shares: Vault::with_bucket(org_shares),
+                policy_resource: policy_resource,
+                policies: Vault::new(policy_resource),
+                policy_count: 0,
+                purchase_resource: purchase_resource,
+                purchase_count: 0,
+                insurer_purchases: HashMap::new(),
+                claim_resource: claim_resource,
+                claim_count: 0,
+                dispute_epochs: dispute_epochs
             }
             .instantiate()
+            .add_access_check(access_rules)
             .globalize();
             (component,org_return_bucket)
         }
 
-        // Create policy
-        pub fn make_policy(&mut self, policy_type: String, coverage: Decimal, price:Decimal, duration:u64, supply: Decimal){
+        // Create policy, returns its id. Only the org can call this
+        pub fn make_policy(&mut self, policy_type: String, coverage: Decimal, price:Decimal, duration:u64, supply: u64) -> u64 {
+            self.create_policy(policy_type, coverage, price, duration, supply, None)
+        }
+
+        // Create a parametric policy, returns its id. Holders of its purchases get the whole coverage with `claim_parametric`
+        // once `oracle` reports `condition` as met during the coverage, without approval.
+        // Only the org can call this (see the access rules in `new_with_dispute_window`), as the oracle decides the payouts
+        pub fn make_parametric_policy(
+            &mut self,
+            policy_type: String,
+            coverage: Decimal,
+            price: Decimal,
+            duration: u64,
+            supply: u64,
+            oracle: ComponentAddress,
+            condition: Condition
+        ) -> u64 {
+            let trigger = Trigger {
+                oracle: oracle,
+                condition: condition
+            };
+            self.create_policy(policy_type, coverage, price, duration, supply, Some(trigger))
+        }
+
+        fn create_policy(
+            &mut self,
+            policy_type: String,
+            coverage: Decimal,
+            price: Decimal,
+            duration: u64,
+            supply: u64,
+            trigger: Option<Trigger>
+        ) -> u64 {
             assert!(coverage > Decimal::zero(), "Coverage cannot be zero");
             assert!(price > Decimal::zero(), "Price cannot be zero");
             assert!(duration > 0, "Duration cannot be zero");
-            assert!(supply > Decimal::zero(), "Supply cannot be zero");
-
-            assert!(self.assets_pool.amount() >= coverage * supply, "You don't have enough assets to cover this supply");
-
-            // policy badge
-            let mut ip_resource_address = ResourceBuilder::new_fungible()
-                .divisibility(DIVISIBILITY_MAXIMUM)
-                .metadata("name", "Insurance Policy badge")
-                .metadata("symbol", policy_type)
-                .metadata("price", price.to_string())
-                .metadata("coverage", coverage.to_string())
-                .metadata("duration", duration.to_string())
-                .mintable(rule!(require(self.org_vault.resource_address())), LOCKED)
-                .burnable(rule!(require(self.org_vault.resource_address())), LOCKED)
-                .no_initial_supply();
-
-            // mint badges
+            assert!(supply > 0, "Supply cannot be zero");
+
+            let volume = coverage * Decimal::from(supply);
+            assert!(self.assets_pool.amount() >= volume, "You don't have enough assets to cover this supply");
+
+            let policy_id = self.policy_count;
+            self.policy_count += 1;
+            let policy = Policy {
+                policy_type: policy_type,
+                coverage: coverage,
+                price: price,
+                duration: duration,
+                trigger: trigger,
+                available: supply
+            };
+
+            // mint the policy badge
+            let policy_resource = self.policy_resource;
             let bucket = self.org_vault.authorize(|| {
-                borrow_resource_manager!(ip_resource_address).mint(supply)
+                borrow_resource_manager!(policy_resource).mint_non_fungible(&NonFungibleId::from_u64(policy_id), policy)
             });
-
-            // new vault
-            let vault = Vault::with_bucket(bucket);
-            self.policies.insert(ip_resource_address, vault);
+            self.policies.put(bucket);
 
             // lock assets
-            let locked = self.assets_pool.take(coverage * supply);
-            self.locked_pool.put(locked)
+            let locked = self.assets_pool.take(volume);
+            self.locked_pool.put(locked);
+
+            policy_id
         }
 
-        // Purchase specific policy by address
-        pub fn purchase(&mut self, policy_address: ResourceAddress, insurer: ComponentAddress, mut bucket: Bucket) -> Bucket {
-            assert!(self.policies.contains_key(&policy_address), "No policy found");
+        // Purchase specific policy by id, returns the rest of the payment and the purchase NFT
+        pub fn purchase(&mut self, policy_id: u64, insurer: ComponentAddress, mut bucket: Bucket) -> (Bucket, Bucket) {
             assert!(bucket.resource_address() == RADIX_TOKEN.into(), "You must purchase policies with Radix (XRD).");
-            
+            let mut policy = self.policy_data(policy_id);
+
             // Don't allow an insurer to buy the exact same policy again
-            let purchases = self.purchases.entry(insurer).or_insert(HashMap::new());
-            assert!(!purchases.contains_key(&policy_address), "The insurer already has this policy");
+            let purchase_ids = self.insurer_purchases.entry(insurer).or_insert(Vec::new());
+            let purchase_resource = self.purchase_resource;
+            let already_purchased = purchase_ids.iter().any(|purchase_id| {
+                let purchase: Purchase = borrow_resource_manager!(purchase_resource).get_non_fungible_data(purchase_id);
+                purchase.policy_id == policy_id && !purchase.released
+            });
+            assert!(!already_purchased, "The insurer already has this policy");
 
             // check if we have free policy in the supply
-            let policies = self.policies.get_mut(&policy_address).unwrap();
-            assert!(!policies.is_empty(), "No available policies");
+            assert!(policy.available > 0, "No available policies");
+            assert!(bucket.amount() >= policy.price, "Not enough amount to purchase this policy");
 
             // take one policy from the supply
-            let policy = policies.take(1);
-            // get metadata
-            let metadata = borrow_resource_manager!(policy.resource_address()).metadata();
-            // check price
-            let price:Decimal = metadata["price"].parse().unwrap();
-            assert!(bucket.amount() >= price, "Not enough amount to purchase this policy");
-
-            // check duration and setup the end epoch
-            let duration:u64 = metadata["duration"].parse().unwrap();
-            let expires = Runtime::current_epoch() + duration;
-
-            // get coverage
-            let coverage:Decimal = metadata["coverage"].parse().unwrap();
-
-            // build and mint policy badge with supply equals to coverage
-            let mut ip_resource_address = ResourceBuilder::new_fungible()
-                .divisibility(DIVISIBILITY_MAXIMUM)
-                .metadata("name", "Insurance Purchase Badge")
-                .metadata("symbol", "IPB")
-                .metadata("expires", expires.to_string())
-                .metadata("policy", policy_address.to_string())
-                .mintable(rule!(require(self.org_vault.resource_address())), LOCKED)
-                .burnable(rule!(require(self.org_vault.resource_address())), LOCKED)
-                .no_initial_supply();
-
-            let ip_badge = self.org_vault.authorize(|| {
-                borrow_resource_manager!(ip_resource_address).mint(coverage)
-            });
-            
-            // burn taken policy
-            self.org_vault.authorize(|| {
-                policy.burn();
-            });
-
-            // update the vault
-            let vault = Vault::with_bucket(ip_badge);
-            purchases.insert(policy_address, vault);           
+            policy.available -= 1;
+
+            let purchase_id = NonFungibleId::from_u64(self.purchase_count);
+            self.purchase_count += 1;
+            purchase_ids.push(purchase_id.clone());
+            let purchase = Purchase {
+                policy_id: policy_id,
+                insurer: insurer,
+                coverage: policy.coverage,
+                purchased_epoch: Runtime::current_epoch(),
+                expires: Runtime::current_epoch() + policy.duration,
+                trigger: policy.trigger.clone(),
+                remaining_coverage: policy.coverage,
+                released: false
+            };
 
             // take payment
-            let payment = bucket.take(price);
+            let payment = bucket.take(policy.price);
             self.assets_pool.put(payment);
 
+            self.update_policy(policy_id, policy);
+            let purchase_nft = self.org_vault.authorize(|| {
+                borrow_resource_manager!(purchase_resource).mint_non_fungible(&purchase_id, purchase)
+            });
+
             // return the rest bucket
-            bucket
+            (bucket, purchase_nft)
         }
 
-        // Approve payment with specific amount to the insurer using policy address 
-        //#[auth(org_badge)]
-        pub fn approve(&mut self, insurer: ComponentAddress, policy_address: ResourceAddress, amount: Decimal){
-            assert!(self.purchases.contains_key(&insurer), "No vault found for this insurer");
+        // Approve payment with specific amount to the insurer of a purchase
+        // Only the org can call this (see the access rules in `new_with_dispute_window`)
+        pub fn approve(&mut self, purchase_id: NonFungibleId, amount: Decimal){
+            let mut purchase = self.purchase_data(&purchase_id);
+            assert!(Runtime::current_epoch() <= purchase.expires, "Policy is expired");
+            assert!(amount <= purchase.remaining_coverage, "Amount is bigger than the remaining coverage");
 
-            let purchases = self.purchases.get_mut(&insurer).unwrap();
-            assert!(purchases.contains_key(&policy_address), "No such policy found for this insurer");
+            purchase.remaining_coverage = purchase.remaining_coverage - amount;
+            let insurer = purchase.insurer;
+            self.update_purchase(&purchase_id, purchase);
 
-            let purchase = purchases.get_mut(&policy_address).unwrap();
-            let bucket = purchase.take(amount);
+            // send XRD from locked pool to the insurer
+            borrow_component!(insurer).call::<()>("deposit", args![self.locked_pool.take(amount)]);
+        }
+
+        // Release the remaining coverage of an expired purchase
+        // Only the org can call this (see the access rules in `new_with_dispute_window`)
+        pub fn burn_purchase(&mut self, purchase_id: NonFungibleId) {
+            let mut purchase = self.purchase_data(&purchase_id);
+            assert!(Runtime::current_epoch() > purchase.expires, "Policy is not expired");
+            assert!(!purchase.released, "The purchase is already released");
+
+            // unlock assets
+            self.assets_pool.put(self.locked_pool.take(purchase.remaining_coverage));
+
+            purchase.remaining_coverage = Decimal::zero();
+            purchase.released = true;
+            self.update_purchase(&purchase_id, purchase);
+        }
+
+        // Burn unused policies
+        // Only the org can call this (see the access rules in `new_with_dispute_window`)
+        pub fn burn_policies(&mut self, policy_id: u64) {
+            info!("Policy Id: {}", policy_id);
+            let policy = self.policy_data(policy_id);
 
-            // get metadata and check if policy is not expired
-            let metadata = borrow_resource_manager!(bucket.resource_address()).metadata();
-            let expires:u64 = metadata["expires"].parse().unwrap();
-            assert!(Runtime::current_epoch() <= expires, "Policy is expired");
+            // calculate the volume of XRD that should be released
+            let volume = policy.coverage * Decimal::from(policy.available);
 
-            let supply = bucket.amount();
-            // Burn purchase badges
+            // Burn the policy badge
+            let bucket = self.policies.take_non_fungible(&NonFungibleId::from_u64(policy_id));
             self.org_vault.authorize(|| {
                 bucket.burn();
             });
-        
-            // send XRD from locked pool to the insurer
-            borrow_component!(insurer).call::<()>("deposit", args![self.locked_pool.take(supply)]);
+
+            // unlock assets
+            self.assets_pool.put(self.locked_pool.take(volume));
+        }
+
+        // Submit a claim of `amount` with a proof of the purchase NFT, returns the claim NFT.
+        // The claimed coverage is reserved until the claim is settled
+        pub fn submit_claim(&mut self, purchase_nft: Proof, amount: Decimal, description: String) -> Bucket {
+            assert_eq!(purchase_nft.resource_address(), self.purchase_resource, "Invalid purchase provided");
+            assert_eq!(purchase_nft.amount(), dec!("1"), "Only one purchase can be claimed at the time");
+            assert!(amount > Decimal::zero(), "Claim amount cannot be zero");
+
+            let nft = purchase_nft.non_fungible::<Purchase>();
+            let purchase_id = nft.id();
+            let mut purchase = nft.data();
+            assert!(Runtime::current_epoch() <= purchase.expires, "Policy is expired");
+            assert!(purchase.remaining_coverage >= amount, "Claim amount is bigger than the remaining coverage");
+
+            purchase.remaining_coverage = purchase.remaining_coverage - amount;
+            self.update_purchase(&purchase_id, purchase);
+
+            let claim_id = NonFungibleId::from_u64(self.claim_count);
+            self.claim_count += 1;
+            let claim = Claim {
+                purchase_id: purchase_id,
+                amount: amount,
+                description: description,
+                status: ClaimStatus::Submitted,
+                decided_epoch: 0,
+                disputed: false
+            };
+            let claim_resource = self.claim_resource;
+            self.org_vault.authorize(|| {
+                borrow_resource_manager!(claim_resource).mint_non_fungible(&claim_id, claim)
+            })
+        }
+
+        // Pay the remaining coverage of a parametric purchase to its insurer, with a proof of the purchase NFT.
+        // The oracle must have reported the condition after the purchase and before the expiry. Anyone holding the NFT can call this
+        pub fn claim_parametric(&mut self, purchase_nft: Proof) {
+            assert_eq!(purchase_nft.resource_address(), self.purchase_resource, "Invalid purchase provided");
+            assert_eq!(purchase_nft.amount(), dec!("1"), "Only one purchase can be claimed at the time");
+
+            let nft = purchase_nft.non_fungible::<Purchase>();
+            let purchase_id = nft.id();
+            let mut purchase = nft.data();
+            let trigger = purchase.trigger.clone().expect("This purchase is not parametric");
+            assert!(!purchase.released, "The purchase is already released");
+
+            // the coverage starts after the purchase epoch
+            let reported_epoch = trigger.reported_epoch(purchase.purchased_epoch, purchase.expires);
+            assert!(reported_epoch.is_some(), "The oracle did not report the condition during the coverage");
+
+            let amount = purchase.remaining_coverage;
+            assert!(amount > Decimal::zero(), "Nothing left to claim");
+            purchase.remaining_coverage = Decimal::zero();
+            let insurer = purchase.insurer;
+            self.update_purchase(&purchase_id, purchase);
+
+            borrow_component!(insurer).call::<()>("deposit", args![self.locked_pool.take(amount)]);
+        }
+
+        // Approve `amount` of a claim, less than claimed for a partial approval
+        // Only the org can call this (see the access rules in `new_with_dispute_window`)
+        pub fn approve_claim(&mut self, claim_id: NonFungibleId, amount: Decimal) {
+            let mut claim = self.open_claim(&claim_id);
+            assert!(amount > Decimal::zero(), "Use reject_claim to approve nothing");
+            assert!(amount <= claim.amount, "Approved amount is bigger than the claim");
+
+            claim.status = ClaimStatus::Approved(amount);
+            claim.decided_epoch = Runtime::current_epoch();
+            self.update_claim(&claim_id, claim);
+        }
+
+        // Only the org can call this (see the access rules in `new_with_dispute_window`)
+        pub fn reject_claim(&mut self, claim_id: NonFungibleId) {
+            let mut claim = self.open_claim(&claim_id);
+
+            claim.status = ClaimStatus::Rejected;
+            claim.decided_epoch = Runtime::current_epoch();
+            self.update_claim(&claim_id, claim);
+        }
+
+        // The holder of a claim NFT disputes the decision on it during the dispute window, the org has to decide again
+        pub fn dispute_claim(&mut self, claim_nft: Proof) {
+            assert_eq!(claim_nft.resource_address(), self.claim_resource, "Invalid claim provided");
+            assert_eq!(claim_nft.amount(), dec!("1"), "Only one claim can be disputed at the time");
+
+            let nft = claim_nft.non_fungible::<Claim>();
+            let claim_id = nft.id();
+            let mut claim = nft.data();
+            assert!(
+                matches!(claim.status, ClaimStatus::Approved(_) | ClaimStatus::Rejected),
+                "Only decided claims can be disputed"
+            );
+            assert!(!claim.disputed, "This claim was already disputed");
+            assert!(Runtime::current_epoch() <= claim.decided_epoch + self.dispute_epochs, "The dispute window is over");
+
+            claim.status = ClaimStatus::Disputed;
+            claim.disputed = true;
+            self.update_claim(&claim_id, claim);
         }
 
-        // Burn expired purchases to release locked assets
-        //#[auth(org_badge)]
-        pub fn burn_purchases(&mut self, insurer: ComponentAddress, policy_address: ResourceAddress) {
-            assert!(self.purchases.contains_key(&insurer), "No vault found for this insurer");
+        // Settle a decided claim once the dispute window is over: the approved amount is paid to the insurer from the locked assets
+        // and the rest of the reserved coverage goes back to the purchase. Anyone can call this
+        pub fn settle_claim(&mut self, claim_id: NonFungibleId) {
+            let mut claim: Claim = borrow_resource_manager!(self.claim_resource).get_non_fungible_data(&claim_id);
+            let approved = match claim.status {
+                ClaimStatus::Approved(amount) => amount,
+                ClaimStatus::Rejected => Decimal::zero(),
+                _ => panic!("This claim is not decided")
+            };
+            assert!(Runtime::current_epoch() > claim.decided_epoch + self.dispute_epochs, "The dispute window is not over");
+
+            let mut purchase = self.purchase_data(&claim.purchase_id);
+            if approved > Decimal::zero() {
+                borrow_component!(purchase.insurer).call::<()>("deposit", args![self.locked_pool.take(approved)]);
+            }
 
-            let purchases = self.purchases.get_mut(&insurer).unwrap();
-            assert!(purchases.contains_key(&policy_address), "No such policy found for this insurer");
+            // the rest of the coverage goes back to the purchase, or is unlocked if the purchase was released meanwhile
+            let rest = claim.amount - approved;
+            if purchase.released {
+                self.assets_pool.put(self.locked_pool.take(rest));
+            } else {
+                purchase.remaining_coverage = purchase.remaining_coverage + rest;
+                self.update_purchase(&claim.purchase_id, purchase);
+            }
 
-            // get all free badges from supply
-            let purchase = purchases.get_mut(&policy_address).unwrap();
-            let bucket = purchase.take_all();
+            claim.status = ClaimStatus::Settled(approved);
+            self.update_claim(&claim_id, claim);
+        }
 
-            // prevent burning non-expired badges
-            let metadata = borrow_resource_manager!(bucket.resource_address()).metadata();
-            let expires:u64 = metadata["expires"].parse().unwrap();
-            assert!(Runtime::current_epoch() > expires, "Policy is not expired");
+        // Policy type, coverage, price, duration, purchases left and trigger of a policy
+        pub fn get_policy(&self, policy_id: u64) -> (String, Decimal, Decimal, u64, u64, Option<Trigger>) {
+            let policy = self.policy_data(policy_id);
+            (policy.policy_type, policy.coverage, policy.price, policy.duration, policy.available, policy.trigger)
+        }
 
-            let supply = bucket.amount();
-            // Burn the the rest purchases
+        // Policy id, insurer, coverage, remaining coverage and expiry epoch of a purchase
+        pub fn get_purchase(&self, purchase_id: NonFungibleId) -> (u64, ComponentAddress, Decimal, Decimal, u64) {
+            let purchase = self.purchase_data(&purchase_id);
+            (purchase.policy_id, purchase.insurer, purchase.coverage, purchase.remaining_coverage, purchase.expires)
+        }
+
+        // (purchase id, policy id, remaining coverage, expiry epoch) of the purchases of an insurer that are not expired and have coverage left.
+        // `insurer` is the payout account given to `purchase`, a purchase NFT moved to another account is still listed under it
+        pub fn get_active_coverage(&self, insurer: ComponentAddress) -> Vec<(NonFungibleId, u64, Decimal, u64)> {
+            let purchase_ids = match self.insurer_purchases.get(&insurer) {
+                Some(purchase_ids) => purchase_ids.clone(),
+                None => Vec::new()
+            };
+            purchase_ids.into_iter()
+                .map(|purchase_id| {
+                    let purchase = self.purchase_data(&purchase_id);
+                    (purchase_id, purchase)
+                })
+                .filter(|(_, purchase)| Runtime::current_epoch() <= purchase.expires && purchase.remaining_coverage > Decimal::zero())
+                .map(|(purchase_id, purchase)| (purchase_id, purchase.policy_id, purchase.remaining_coverage, purchase.expires))
+                .collect()
+        }
+
+        // Purchase, claimed amount, description, status and epoch of the last decision of a claim
+        pub fn get_claim(&self, claim_id: NonFungibleId) -> (NonFungibleId, Decimal, String, ClaimStatus, u64) {
+            let claim: Claim = borrow_resource_manager!(self.claim_resource).get_non_fungible_data(&claim_id);
+            (claim.purchase_id, claim.amount, claim.description, claim.status, claim.decided_epoch)
+        }
+
+        fn policy_data(&self, policy_id: u64) -> Policy {
+            assert!(policy_id < self.policy_count, "No policy found");
+            let policy_id = NonFungibleId::from_u64(policy_id);
+            assert!(self.policies.non_fungible_ids().contains(&policy_id), "The policy was burned");
+            borrow_resource_manager!(self.policy_resource).get_non_fungible_data(&policy_id)
+        }
+
+        fn update_policy(&self, policy_id: u64, policy: Policy) {
+            let policy_resource = self.policy_resource;
             self.org_vault.authorize(|| {
-                bucket.burn();
+                borrow_resource_manager!(policy_resource).update_non_fungible_data(&NonFungibleId::from_u64(policy_id), policy)
             });
+        }
 
-            // unlock assets
-            self.assets_pool.put(self.locked_pool.take(supply));
+        fn purchase_data(&self, purchase_id: &NonFungibleId) -> Purchase {
+            borrow_resource_manager!(self.purchase_resource).get_non_fungible_data(purchase_id)
+        }
+
+        fn update_purchase(&self, purchase_id: &NonFungibleId, purchase: Purchase) {
+            let purchase_resource = self.purchase_resource;
+            self.org_vault.authorize(|| {
+                borrow_resource_manager!(purchase_resource).update_non_fungible_data(purchase_id, purchase)
+            });
+        }
 
-            // clear purchases
-            self.dead_vaults.push(purchases.remove(&policy_address).unwrap());
+        // Claim waiting for a decision of the org
+        fn open_claim(&self, claim_id: &NonFungibleId) -> Claim {
+            let claim: Claim = borrow_resource_manager!(self.claim_resource).get_non_fungible_data(claim_id);
+            assert!(
+                matches!(claim.status, ClaimStatus::Submitted | ClaimStatus::Disputed),
+                "This claim is already decided"
+            );
+            claim
         }
 
-        // Burn unused policies
-        //#[auth(org_badge)]
-        pub fn burn_policies(&mut self, policy_address: ResourceAddress) {
-            info!("Policy Address: {}", policy_address);
-            info!("Policies: {:?}", self.policies);
-            assert!(self.policies.contains_key(&policy_address), "No policy found");
-
-            // take the rest supply of policy badges
-            let policies = self.policies.get_mut(&policy_address).unwrap();
-            let bucket = policies.take_all();
-
-            // get coverage and calculate the volume of XRD that should be released
-            let metadata = borrow_resource_manager!(bucket.resource_address()).metadata();
-            let coverage:Decimal = metadata["coverage"].parse().unwrap();
-            let volume = bucket.amount()*coverage;
-
-            // Burn the the rest purchases
+        fn update_claim(&self, claim_id: &NonFungibleId, claim: Claim) {
+            let claim_resource = self.claim_resource;
             self.org_vault.authorize(|| {
-                bucket.burn();
+                borrow_resource_manager!(claim_resource).update_non_fungible_data(claim_id, claim)
             });
+        }
 
-            // unlock assets
-            self.assets_pool.put(self.locked_pool.take(volume));
+        // Deposit XRD to underwrite policies, returns shares of the assets.
+        // Shares earn premiums and bear payouts pro rata
+        pub fn underwrite(&mut self, bucket: Bucket) -> Bucket {
+            assert!(bucket.resource_address() == RADIX_TOKEN.into(), "You must underwrite with Radix (XRD).");
+            assert!(bucket.amount() > Decimal::zero(), "You cannot underwrite zero amount");
+
+            let shares = self.shares_for(bucket.amount());
+            assert!(shares > Decimal::zero(), "The deposit is too small for a share");
+            self.assets_pool.put(bucket);
 
-            // clear policies
-            self.dead_vaults.push(self.policies.remove(&policy_address).unwrap());
+            let share_resource = self.share_resource;
+            self.org_vault.authorize(|| {
+                borrow_resource_manager!(share_resource).mint(shares)
+            })
         }
 
-        /// Org assets methods
-        pub fn deposit(&mut self, bucket: Bucket) {
-            assert!(bucket.resource_address() == RADIX_TOKEN.into(), "You must deposit with Radix (XRD).");
-            assert!(bucket.amount() > Decimal::zero(), "You cannot deposit zero amount");
+        // Burn shares for the XRD they are worth, only from the assets that don't back policies
+        pub fn withdraw_underwriting(&mut self, shares: Bucket) -> Bucket {
+            assert!(shares.resource_address() == self.share_resource, "Invalid shares provided");
+
+            let amount = self.value_of(shares.amount());
+            assert!(amount <= self.assets_pool.amount(), "Withdraw amount is bigger than unlocked assets");
+            self.org_vault.authorize(|| {
+                shares.burn();
+            });
+
+            self.assets_pool.take(amount)
+        }
 
-            self.assets_pool.put(bucket)
+        // XRD one share is worth
+        pub fn get_share_value(&self) -> Decimal {
+            self.value_of(Decimal::one())
+        }
+
+        pub fn get_share_resource(&self) -> ResourceAddress {
+            self.share_resource
+        }
+
+        /// Org assets methods, the org underwrites with shares kept by the component.
+        /// Only the org can call them (see the access rules in `new_with_dispute_window`)
+        pub fn deposit(&mut self, bucket: Bucket) {
+            let shares = self.underwrite(bucket);
+            self.org_shares.put(shares);
         }
 
         pub fn withdraw(&mut self, amount: Decimal) -> Bucket {
             assert!(self.assets_pool.amount() >= amount, "Withdraw amount is bigger than available assets");
 
+            // shares worth at least `amount`
+            let mut shares = self.shares_for(amount);
+            if self.value_of(shares) < amount {
+                shares = shares + Decimal(1);
+            }
+            assert!(self.org_shares.amount() >= shares, "Withdraw amount is bigger than the org shares");
+            let org_shares = self.org_shares.take(shares);
+            self.org_vault.authorize(|| {
+                org_shares.burn();
+            });
+
             self.assets_pool.take(amount)
         }
 
+        // Shares of the org, other shares are held by underwriters
+        pub fn org_shares(&self) -> Decimal {
+            self.org_shares.amount()
+        }
+
+        // Shares for a deposit of `amount`, rounded down
+        fn shares_for(&self, amount: Decimal) -> Decimal {
+            let supply = borrow_resource_manager!(self.share_resource).total_supply();
+            let capital = self.assets_pool.amount() + self.locked_pool.amount();
+            if supply.is_zero() {
+                amount
+            } else {
+                assert!(!capital.is_zero(), "All the assets were paid out, the shares are worth nothing");
+                amount * supply / capital
+            }
+        }
+
+        // XRD `shares` are worth, rounded down
+        fn value_of(&self, shares: Decimal) -> Decimal {
+            let supply = borrow_resource_manager!(self.share_resource).total_supply();
+            let capital = self.assets_pool.amount() + self.locked_pool.amount();
+            if supply.is_zero() {
+                Decimal::zero()
+            } else {
+                shares * capital / supply
+            }
+        }
+
         pub fn assets(&mut self) -> Decimal {
             self.assets_pool.amount()
         }
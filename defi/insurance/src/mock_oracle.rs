@@ -0,0 +1,41 @@
+use scrypto::prelude::*;
+
+blueprint! {
+    /// Oracle for parametric policies in tests and local runs, anyone can report.
+    /// A real oracle only has to provide `get_values` and `get_flags`
+    struct MockOracle {
+        // feed -> every value reported and its epoch
+        values: HashMap<String, Vec<(Decimal, u64)>>,
+        // feed -> epochs the event was reported
+        flags: HashMap<String, Vec<u64>>
+    }
+
+    impl MockOracle {
+        pub fn new() -> ComponentAddress {
+            Self {
+                values: HashMap::new(),
+                flags: HashMap::new()
+            }
+            .instantiate()
+            .globalize()
+        }
+
+        pub fn set_value(&mut self, feed: String, value: Decimal) {
+            self.values.entry(feed).or_insert(Vec::new()).push((value, Runtime::current_epoch()));
+        }
+
+        pub fn set_flag(&mut self, feed: String) {
+            self.flags.entry(feed).or_insert(Vec::new()).push(Runtime::current_epoch());
+        }
+
+        // Values reported on a feed with their epochs, oldest first
+        pub fn get_values(&self, feed: String) -> Vec<(Decimal, u64)> {
+            self.values.get(&feed).cloned().unwrap_or_default()
+        }
+
+        // Epochs an event was reported, empty if it wasn't
+        pub fn get_flags(&self, feed: String) -> Vec<u64> {
+            self.flags.get(&feed).cloned().unwrap_or_default()
+        }
+    }
+}
@@ -0,0 +1,524 @@
+use insurance::{ClaimStatus, Condition};
+use radix_engine::ledger::*;
+use radix_engine::transaction::*;
+use scrypto::prelude::*;
+
+struct Account {
+    pk: EcdsaPublicKey,
+    sk: EcdsaPrivateKey,
+    address: ComponentAddress,
+}
+
+struct Setup {
+    account: Account,
+    insurance: ComponentAddress,
+    oracle: ComponentAddress,
+    purchase_resource: ResourceAddress,
+}
+
+fn execute(executor: &mut TransactionExecutor<InMemorySubstateStore>, account: &Account, builder: &mut TransactionBuilder) -> Receipt {
+    let transaction = builder
+        .call_method_with_all_resources(account.address, "deposit_batch")
+        .build(executor.get_nonce([account.pk]))
+        .sign([&account.sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    println!("{:?}\n", receipt);
+    receipt
+}
+
+fn run(executor: &mut TransactionExecutor<InMemorySubstateStore>, account: &Account, builder: &mut TransactionBuilder) -> Receipt {
+    let receipt = execute(executor, account, builder);
+    assert!(receipt.result.is_ok());
+    receipt
+}
+
+fn balance(executor: &mut TransactionExecutor<InMemorySubstateStore>, account: &Account, resource: ResourceAddress) -> Decimal {
+    let receipt = run(executor, account, TransactionBuilder::new().call_method(account.address, "balance", args![resource]));
+    scrypto_decode(&receipt.outputs[0].raw[..]).unwrap()
+}
+
+// Insurance with 1000 XRD, a mock oracle and a parametric policy of 100 XRD coverage for 10 XRD over 50 epochs,
+// bought at epoch 0 with the account as insurer
+fn setup(executor: &mut TransactionExecutor<InMemorySubstateStore>, condition: Condition) -> Setup {
+    let (pk, sk, address) = executor.new_account();
+    let account = Account { pk, sk, address };
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let receipt = run(
+        executor,
+        &account,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(dec!("1000"), RADIX_TOKEN, account.address)
+            .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+                builder.call_function(package, "Insurance", "new", args![scrypto::resource::Bucket(bucket_id)])
+            }),
+    );
+    let insurance = receipt.new_component_addresses[0];
+    // org badge, policy, purchase, claim and share resources
+    let org_badge = receipt.new_resource_addresses[0];
+    let purchase_resource = receipt.new_resource_addresses[2];
+
+    let receipt = run(executor, &account, TransactionBuilder::new().call_function(package, "MockOracle", "new", args![]));
+    let oracle = receipt.new_component_addresses[0];
+
+    // only the org creates parametric policies
+    let policy_args = args!["price".to_string(), dec!("100"), dec!("10"), 50u64, 2u64, oracle, condition];
+    let receipt = execute(executor, &account, TransactionBuilder::new().call_method(insurance, "make_parametric_policy", policy_args.clone()));
+    assert!(receipt.result.is_err());
+    run(
+        executor,
+        &account,
+        TransactionBuilder::new()
+            .create_proof_from_account_by_amount(dec!("1"), org_badge, account.address)
+            .call_method(insurance, "make_parametric_policy", policy_args),
+    );
+    run(
+        executor,
+        &account,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(dec!("10"), RADIX_TOKEN, account.address)
+            .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+                builder.call_method(insurance, "purchase", args![0u64, account.address, scrypto::resource::Bucket(bucket_id)])
+            }),
+    );
+
+    Setup { account, insurance, oracle, purchase_resource }
+}
+
+fn set_value(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Setup, feed: &str, value: Decimal) {
+    run(executor, &setup.account, TransactionBuilder::new().call_method(setup.oracle, "set_value", args![feed.to_string(), value]));
+}
+
+fn claim(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Setup) -> Receipt {
+    execute(
+        executor,
+        &setup.account,
+        TransactionBuilder::new()
+            .create_proof_from_account_by_amount(dec!("1"), setup.purchase_resource, setup.account.address)
+            .pop_from_auth_zone(|builder, proof_id| {
+                builder.call_method(setup.insurance, "claim_parametric", args![scrypto::resource::Proof(proof_id)])
+            }),
+    )
+}
+
+#[test]
+fn test_claim_pays_the_coverage_once_the_oracle_reports() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup(&mut executor, Condition::Below("XRD/USD".to_string(), dec!("0.05")));
+
+    // nothing reported yet, then a value above the threshold
+    assert!(claim(&mut executor, &setup).result.is_err());
+    executor.substate_store_mut().set_epoch(3);
+    set_value(&mut executor, &setup, "XRD/USD", dec!("0.06"));
+    assert!(claim(&mut executor, &setup).result.is_err());
+
+    executor.substate_store_mut().set_epoch(5);
+    set_value(&mut executor, &setup, "XRD/USD", dec!("0.04"));
+    let before = balance(&mut executor, &setup.account, RADIX_TOKEN);
+    assert!(claim(&mut executor, &setup).result.is_ok());
+    assert_eq!(balance(&mut executor, &setup.account, RADIX_TOKEN) - before, dec!("100"));
+
+    // the coverage is paid only once
+    assert!(claim(&mut executor, &setup).result.is_err());
+}
+
+#[test]
+fn test_breach_reverted_by_a_later_report_can_be_claimed() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup(&mut executor, Condition::Below("XRD/USD".to_string(), dec!("0.05")));
+
+    executor.substate_store_mut().set_epoch(5);
+    set_value(&mut executor, &setup, "XRD/USD", dec!("0.04"));
+    executor.substate_store_mut().set_epoch(6);
+    set_value(&mut executor, &setup, "XRD/USD", dec!("0.06"));
+
+    let before = balance(&mut executor, &setup.account, RADIX_TOKEN);
+    assert!(claim(&mut executor, &setup).result.is_ok());
+    assert_eq!(balance(&mut executor, &setup.account, RADIX_TOKEN) - before, dec!("100"));
+}
+
+#[test]
+fn test_report_after_the_expiry_does_not_hide_a_breach() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup(&mut executor, Condition::Above("ETH/USD".to_string(), dec!("5000")));
+
+    executor.substate_store_mut().set_epoch(20);
+    set_value(&mut executor, &setup, "ETH/USD", dec!("6000"));
+    executor.substate_store_mut().set_epoch(60);
+    set_value(&mut executor, &setup, "ETH/USD", dec!("7000"));
+    assert!(claim(&mut executor, &setup).result.is_ok());
+}
+
+#[test]
+fn test_report_at_the_purchase_epoch_cannot_be_claimed() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup(&mut executor, Condition::Below("XRD/USD".to_string(), dec!("0.05")));
+
+    set_value(&mut executor, &setup, "XRD/USD", dec!("0.04"));
+    assert!(claim(&mut executor, &setup).result.is_err());
+}
+
+#[test]
+fn test_report_after_the_expiry_cannot_be_claimed() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup(&mut executor, Condition::Above("ETH/USD".to_string(), dec!("5000")));
+
+    executor.substate_store_mut().set_epoch(51);
+    set_value(&mut executor, &setup, "ETH/USD", dec!("6000"));
+    assert!(claim(&mut executor, &setup).result.is_err());
+}
+
+#[test]
+fn test_event_flag_triggers_the_claim() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup(&mut executor, Condition::Event("hurricane".to_string()));
+
+    executor.substate_store_mut().set_epoch(10);
+    run(&mut executor, &setup.account, TransactionBuilder::new().call_method(setup.oracle, "set_flag", args!["hurricane".to_string()]));
+    let before = balance(&mut executor, &setup.account, RADIX_TOKEN);
+    assert!(claim(&mut executor, &setup).result.is_ok());
+    assert_eq!(balance(&mut executor, &setup.account, RADIX_TOKEN) - before, dec!("100"));
+}
+
+struct Underwriting {
+    account: Account,
+    insurance: ComponentAddress,
+    org_badge: ResourceAddress,
+    purchase_resource: ResourceAddress,
+    claim_resource: ResourceAddress,
+    share_resource: ResourceAddress,
+}
+
+// Insurance with 100 XRD of the org and 100 XRD of the account as underwriter
+fn setup_underwriting(executor: &mut TransactionExecutor<InMemorySubstateStore>) -> Underwriting {
+    let (pk, sk, address) = executor.new_account();
+    let account = Account { pk, sk, address };
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let receipt = run(
+        executor,
+        &account,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(dec!("100"), RADIX_TOKEN, account.address)
+            .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+                builder.call_function(package, "Insurance", "new", args![scrypto::resource::Bucket(bucket_id)])
+            }),
+    );
+    let insurance = receipt.new_component_addresses[0];
+    // org badge, policy, purchase, claim and share resources
+    let org_badge = receipt.new_resource_addresses[0];
+    let purchase_resource = receipt.new_resource_addresses[2];
+    let claim_resource = receipt.new_resource_addresses[3];
+    let share_resource = receipt.new_resource_addresses[4];
+
+    run(
+        executor,
+        &account,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(dec!("100"), RADIX_TOKEN, account.address)
+            .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+                builder.call_method(insurance, "underwrite", args![scrypto::resource::Bucket(bucket_id)])
+            }),
+    );
+
+    Underwriting { account, insurance, org_badge, purchase_resource, claim_resource, share_resource }
+}
+
+fn as_org(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Underwriting, method: &str, args: Vec<Vec<u8>>) -> Receipt {
+    execute(
+        executor,
+        &setup.account,
+        TransactionBuilder::new()
+            .create_proof_from_account_by_amount(dec!("1"), setup.org_badge, setup.account.address)
+            .call_method(setup.insurance, method, args),
+    )
+}
+
+fn share_value(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Underwriting) -> Decimal {
+    let receipt = run(executor, &setup.account, TransactionBuilder::new().call_method(setup.insurance, "get_share_value", args![]));
+    scrypto_decode(&receipt.outputs[0].raw[..]).unwrap()
+}
+
+fn withdraw_underwriting(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Underwriting, shares: Decimal) -> Receipt {
+    execute(
+        executor,
+        &setup.account,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(shares, setup.share_resource, setup.account.address)
+            .take_from_worktop(setup.share_resource, |builder, bucket_id| {
+                builder.call_method(setup.insurance, "withdraw_underwriting", args![scrypto::resource::Bucket(bucket_id)])
+            }),
+    )
+}
+
+#[test]
+fn test_premiums_raise_and_payouts_lower_the_share_value() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup_underwriting(&mut executor);
+    assert_eq!(balance(&mut executor, &setup.account, setup.share_resource), dec!("100"));
+    assert_eq!(share_value(&mut executor, &setup), dec!("1"));
+
+    // only the org creates policies
+    let policy_args = args!["property".to_string(), dec!("50"), dec!("10"), 100u64, 2u64];
+    assert!(execute(&mut executor, &setup.account, TransactionBuilder::new().call_method(setup.insurance, "make_policy", policy_args.clone())).result.is_err());
+    assert!(as_org(&mut executor, &setup, "make_policy", policy_args).result.is_ok());
+
+    // a premium of 10 XRD on 200 shares
+    run(
+        &mut executor,
+        &setup.account,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(dec!("10"), RADIX_TOKEN, setup.account.address)
+            .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+                builder.call_method(setup.insurance, "purchase", args![0u64, setup.account.address, scrypto::resource::Bucket(bucket_id)])
+            }),
+    );
+    assert_eq!(share_value(&mut executor, &setup), dec!("1.05"));
+
+    // a payout of 30 XRD
+    assert!(as_org(&mut executor, &setup, "approve", args![NonFungibleId::from_u64(0), dec!("30")]).result.is_ok());
+    assert_eq!(share_value(&mut executor, &setup), dec!("0.9"));
+
+    let before = balance(&mut executor, &setup.account, RADIX_TOKEN);
+    assert!(withdraw_underwriting(&mut executor, &setup, dec!("50")).result.is_ok());
+    assert_eq!(balance(&mut executor, &setup.account, RADIX_TOKEN) - before, dec!("45"));
+}
+
+#[test]
+fn test_withdraw_underwriting_is_limited_to_unlocked_assets() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup_underwriting(&mut executor);
+
+    // 150 of the 200 XRD back the policy
+    let policy_args = args!["property".to_string(), dec!("150"), dec!("10"), 100u64, 1u64];
+    assert!(as_org(&mut executor, &setup, "make_policy", policy_args).result.is_ok());
+
+    assert!(withdraw_underwriting(&mut executor, &setup, dec!("100")).result.is_err());
+    assert!(withdraw_underwriting(&mut executor, &setup, dec!("50")).result.is_ok());
+    assert!(withdraw_underwriting(&mut executor, &setup, dec!("1")).result.is_err());
+
+    // the org can't take the locked assets either
+    assert!(as_org(&mut executor, &setup, "withdraw", args![dec!("1")]).result.is_err());
+}
+
+// Policy of 50 XRD coverage for 10 XRD over 100 epochs, bought at epoch 0 with the account as insurer
+fn buy_policy(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Underwriting) {
+    let policy_args = args!["property".to_string(), dec!("50"), dec!("10"), 100u64, 1u64];
+    assert!(as_org(executor, setup, "make_policy", policy_args).result.is_ok());
+    run(
+        executor,
+        &setup.account,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(dec!("10"), RADIX_TOKEN, setup.account.address)
+            .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+                builder.call_method(setup.insurance, "purchase", args![0u64, setup.account.address, scrypto::resource::Bucket(bucket_id)])
+            }),
+    );
+}
+
+fn submit_claim(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Underwriting, amount: Decimal) -> Receipt {
+    execute(
+        executor,
+        &setup.account,
+        TransactionBuilder::new()
+            .create_proof_from_account_by_amount(dec!("1"), setup.purchase_resource, setup.account.address)
+            .pop_from_auth_zone(|builder, proof_id| {
+                builder.call_method(setup.insurance, "submit_claim", args![scrypto::resource::Proof(proof_id), amount, "roof damage".to_string()])
+            }),
+    )
+}
+
+fn dispute_claim(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Underwriting) -> Receipt {
+    execute(
+        executor,
+        &setup.account,
+        TransactionBuilder::new()
+            .create_proof_from_account_by_amount(dec!("1"), setup.claim_resource, setup.account.address)
+            .pop_from_auth_zone(|builder, proof_id| {
+                builder.call_method(setup.insurance, "dispute_claim", args![scrypto::resource::Proof(proof_id)])
+            }),
+    )
+}
+
+fn settle_claim(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Underwriting) -> Receipt {
+    execute(executor, &setup.account, TransactionBuilder::new().call_method(setup.insurance, "settle_claim", args![NonFungibleId::from_u64(0)]))
+}
+
+fn claim_status(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Underwriting) -> ClaimStatus {
+    let receipt = run(executor, &setup.account, TransactionBuilder::new().call_method(setup.insurance, "get_claim", args![NonFungibleId::from_u64(0)]));
+    let (_, _, _, status, _): (NonFungibleId, Decimal, String, ClaimStatus, u64) = scrypto_decode(&receipt.outputs[0].raw[..]).unwrap();
+    status
+}
+
+fn remaining_coverage(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Underwriting) -> Decimal {
+    let receipt = run(executor, &setup.account, TransactionBuilder::new().call_method(setup.insurance, "get_purchase", args![NonFungibleId::from_u64(0)]));
+    let (_, _, _, remaining_coverage, _): (u64, ComponentAddress, Decimal, Decimal, u64) = scrypto_decode(&receipt.outputs[0].raw[..]).unwrap();
+    remaining_coverage
+}
+
+fn pool(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Underwriting, method: &str) -> Decimal {
+    let receipt = run(executor, &setup.account, TransactionBuilder::new().call_method(setup.insurance, method, args![]));
+    scrypto_decode(&receipt.outputs[0].raw[..]).unwrap()
+}
+
+#[test]
+fn test_approved_claim_is_paid_after_the_dispute_window() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup_underwriting(&mut executor);
+    buy_policy(&mut executor, &setup);
+
+    // the claimed coverage is reserved
+    assert!(submit_claim(&mut executor, &setup, dec!("30")).result.is_ok());
+    assert_eq!(claim_status(&mut executor, &setup), ClaimStatus::Submitted);
+    assert_eq!(remaining_coverage(&mut executor, &setup), dec!("20"));
+    assert!(submit_claim(&mut executor, &setup, dec!("30")).result.is_err());
+
+    // only the org decides
+    let approve_args = args![NonFungibleId::from_u64(0), dec!("30")];
+    assert!(execute(&mut executor, &setup.account, TransactionBuilder::new().call_method(setup.insurance, "approve_claim", approve_args.clone())).result.is_err());
+    assert!(as_org(&mut executor, &setup, "approve_claim", approve_args).result.is_ok());
+    assert_eq!(claim_status(&mut executor, &setup), ClaimStatus::Approved(dec!("30")));
+
+    // the decision can be disputed for 10 epochs
+    executor.substate_store_mut().set_epoch(10);
+    assert!(settle_claim(&mut executor, &setup).result.is_err());
+    executor.substate_store_mut().set_epoch(11);
+    let before = balance(&mut executor, &setup.account, RADIX_TOKEN);
+    assert!(settle_claim(&mut executor, &setup).result.is_ok());
+    assert_eq!(balance(&mut executor, &setup.account, RADIX_TOKEN) - before, dec!("30"));
+    assert_eq!(claim_status(&mut executor, &setup), ClaimStatus::Settled(dec!("30")));
+    assert_eq!(remaining_coverage(&mut executor, &setup), dec!("20"));
+    assert_eq!(pool(&mut executor, &setup, "locked"), dec!("20"));
+
+    // a claim is settled once
+    assert!(settle_claim(&mut executor, &setup).result.is_err());
+}
+
+#[test]
+fn test_partially_approved_claim_can_be_disputed_once() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup_underwriting(&mut executor);
+    buy_policy(&mut executor, &setup);
+
+    assert!(submit_claim(&mut executor, &setup, dec!("30")).result.is_ok());
+    // a claim that is not decided yet cannot be disputed
+    assert!(dispute_claim(&mut executor, &setup).result.is_err());
+    assert!(as_org(&mut executor, &setup, "approve_claim", args![NonFungibleId::from_u64(0), dec!("40")]).result.is_err());
+    assert!(as_org(&mut executor, &setup, "approve_claim", args![NonFungibleId::from_u64(0), dec!("10")]).result.is_ok());
+
+    executor.substate_store_mut().set_epoch(5);
+    assert!(dispute_claim(&mut executor, &setup).result.is_ok());
+    assert_eq!(claim_status(&mut executor, &setup), ClaimStatus::Disputed);
+    assert!(settle_claim(&mut executor, &setup).result.is_err());
+
+    // the org decides again, and that decision is final
+    assert!(as_org(&mut executor, &setup, "approve_claim", args![NonFungibleId::from_u64(0), dec!("20")]).result.is_ok());
+    assert!(dispute_claim(&mut executor, &setup).result.is_err());
+
+    // the rest of the claim goes back to the coverage of the purchase
+    executor.substate_store_mut().set_epoch(16);
+    let before = balance(&mut executor, &setup.account, RADIX_TOKEN);
+    assert!(settle_claim(&mut executor, &setup).result.is_ok());
+    assert_eq!(balance(&mut executor, &setup.account, RADIX_TOKEN) - before, dec!("20"));
+    assert_eq!(remaining_coverage(&mut executor, &setup), dec!("30"));
+    assert_eq!(pool(&mut executor, &setup, "locked"), dec!("30"));
+}
+
+#[test]
+fn test_rejected_claim_cannot_be_disputed_after_the_window() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup_underwriting(&mut executor);
+    buy_policy(&mut executor, &setup);
+
+    assert!(submit_claim(&mut executor, &setup, dec!("30")).result.is_ok());
+    assert!(execute(&mut executor, &setup.account, TransactionBuilder::new().call_method(setup.insurance, "reject_claim", args![NonFungibleId::from_u64(0)])).result.is_err());
+    assert!(as_org(&mut executor, &setup, "reject_claim", args![NonFungibleId::from_u64(0)]).result.is_ok());
+    assert_eq!(claim_status(&mut executor, &setup), ClaimStatus::Rejected);
+
+    executor.substate_store_mut().set_epoch(11);
+    assert!(dispute_claim(&mut executor, &setup).result.is_err());
+
+    // nothing is paid and the whole claim goes back to the coverage
+    let before = balance(&mut executor, &setup.account, RADIX_TOKEN);
+    assert!(settle_claim(&mut executor, &setup).result.is_ok());
+    assert_eq!(balance(&mut executor, &setup.account, RADIX_TOKEN), before);
+    assert_eq!(claim_status(&mut executor, &setup), ClaimStatus::Settled(dec!("0")));
+    assert_eq!(remaining_coverage(&mut executor, &setup), dec!("50"));
+}
+
+#[test]
+fn test_claim_settled_after_the_purchase_is_burned_unlocks_the_rest() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup_underwriting(&mut executor);
+    buy_policy(&mut executor, &setup);
+    assert_eq!(pool(&mut executor, &setup, "assets"), dec!("160"));
+
+    assert!(submit_claim(&mut executor, &setup, dec!("30")).result.is_ok());
+    executor.substate_store_mut().set_epoch(95);
+    assert!(as_org(&mut executor, &setup, "approve_claim", args![NonFungibleId::from_u64(0), dec!("10")]).result.is_ok());
+
+    // the coverage that is not claimed is unlocked with the purchase
+    executor.substate_store_mut().set_epoch(101);
+    assert!(as_org(&mut executor, &setup, "burn_purchase", args![NonFungibleId::from_u64(0)]).result.is_ok());
+    assert_eq!(pool(&mut executor, &setup, "assets"), dec!("180"));
+    assert_eq!(pool(&mut executor, &setup, "locked"), dec!("30"));
+
+    // and the rest of the claim when it is settled
+    executor.substate_store_mut().set_epoch(106);
+    let before = balance(&mut executor, &setup.account, RADIX_TOKEN);
+    assert!(settle_claim(&mut executor, &setup).result.is_ok());
+    assert_eq!(balance(&mut executor, &setup.account, RADIX_TOKEN) - before, dec!("10"));
+    assert_eq!(pool(&mut executor, &setup, "assets"), dec!("200"));
+    assert_eq!(pool(&mut executor, &setup, "locked"), dec!("0"));
+}
+
+fn active_coverage(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Underwriting) -> Vec<(NonFungibleId, u64, Decimal, u64)> {
+    let receipt = run(executor, &setup.account, TransactionBuilder::new().call_method(setup.insurance, "get_active_coverage", args![setup.account.address]));
+    scrypto_decode(&receipt.outputs[0].raw[..]).unwrap()
+}
+
+#[test]
+fn test_active_coverage_skips_paid_and_expired_purchases() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup_underwriting(&mut executor);
+    buy_policy(&mut executor, &setup);
+    assert_eq!(active_coverage(&mut executor, &setup), vec![(NonFungibleId::from_u64(0), 0, dec!("50"), 100)]);
+
+    // fully paid
+    assert!(as_org(&mut executor, &setup, "approve", args![NonFungibleId::from_u64(0), dec!("50")]).result.is_ok());
+    assert_eq!(active_coverage(&mut executor, &setup), vec![]);
+
+    // a second policy, bought at epoch 10
+    executor.substate_store_mut().set_epoch(10);
+    let policy_args = args!["flood".to_string(), dec!("20"), dec!("5"), 50u64, 1u64];
+    assert!(as_org(&mut executor, &setup, "make_policy", policy_args).result.is_ok());
+    run(
+        &mut executor,
+        &setup.account,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(dec!("5"), RADIX_TOKEN, setup.account.address)
+            .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+                builder.call_method(setup.insurance, "purchase", args![1u64, setup.account.address, scrypto::resource::Bucket(bucket_id)])
+            }),
+    );
+    assert_eq!(active_coverage(&mut executor, &setup), vec![(NonFungibleId::from_u64(1), 1, dec!("20"), 60)]);
+
+    // expired
+    executor.substate_store_mut().set_epoch(60);
+    assert_eq!(active_coverage(&mut executor, &setup).len(), 1);
+    executor.substate_store_mut().set_epoch(61);
+    assert_eq!(active_coverage(&mut executor, &setup), vec![]);
+}
@@ -55,7 +55,8 @@ setEnvValue('privkey2',privkey2);
 const pub_package = (await e($`resim publish .`))[0]
 setEnvValue('package',pub_package);
 
-output = await e($`resim call-function  ${pub_package} LiquidityPool new 1000,${tokenXRD} "LPT" "LP_Token"`)
+// no fee vesting, so every collected fee is credited to the pool right away
+output = await e($`resim call-function  ${pub_package} LiquidityPool new_with_fee_vesting 1000,${tokenXRD} "LPT" "LP_Token" 0`)
 const component= output[0]
 const lp_mint_bage= output[1]
 const lp_token = output[2]
@@ -82,16 +83,17 @@ await e($`resim call-method ${component} add_collected_fee 500,${tokenXRD}`)
 
 // Removee all liquidity in the pool
 
+// the pool keeps 0.000001 of the initial LP tokens
 await e($`resim set-default-account ${account} ${privkey}`)
-await e($`resim call-method ${component} remove_liquidity 1000,${lp_token}`)
+await e($`resim call-method ${component} remove_liquidity 999.999999,${lp_token}`)
 
 await e($`resim set-default-account ${account1} ${privkey1}`)
 await e($`resim call-method ${component} remove_liquidity 1000,${lp_token}`)
 
 await e($`resim set-default-account ${account2} ${privkey2}`)
-await e($`resim call-method ${component} remove_liquidity 869.565217391304347,${lp_token}`)
+await e($`resim call-method ${component} remove_liquidity 869.565217391304347826,${lp_token}`)
 
-// Add liquidity to th empty pool
+// Add liquidity to the pool, only the LP tokens kept by the pool are left
 
 await e($`resim set-default-account ${account} ${privkey}`)
 await e($`resim call-method ${component} add_liquidity 1000,${tokenXRD}`)
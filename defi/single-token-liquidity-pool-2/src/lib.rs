@@ -1,129 +1,119 @@
 use scrypto::prelude::*;
+use single_token_pool::{SingleTokenPool, DEFAULT_VESTING_EPOCHS};
 
 blueprint! {
+    /// Kept for the components using this API, the accounting is done by `SingleTokenPool`
+    /// from the `single_token_pool` package.
     struct LiquidityPool {
-        pool: Vault,
-        lp_mint_badge: Vault,
-        lp_resource_address: ResourceAddress,
-        lp_per_asset_ratio:Decimal,
+        pool: SingleTokenPool,
     }
 
     impl LiquidityPool {
         /// Creates a LiquidityPool component and returns the component address
-        /// along with the initial LP tokens.
+        /// along with the initial LP tokens, one per token minus the few kept by the pool.
         pub fn new(
             tokens: Bucket,
             lp_symbol: String,
             lp_name: String,
         ) -> (ComponentAddress,Bucket) {
+            Self::new_with_fee_vesting(tokens, lp_symbol, lp_name, DEFAULT_VESTING_EPOCHS)
+        }
 
-            // Check arguments
-            assert!(
-                !tokens.is_empty(),
-                "You must pass in an initial supply of token"
-            );
-            assert_ne!(
-                borrow_resource_manager!(tokens.resource_address()).resource_type(),
-                ResourceType::NonFungible,
-                "A liquidity pool can only have fungible tokens."
-            );
-
-            // Mint the badge needed to mint LP Tokens
-            let lp_mint_badge = ResourceBuilder::new_fungible()
-                .divisibility(DIVISIBILITY_NONE)
-                .metadata("name", "LP Token Mint Auth")
-                .initial_supply(1);
-
-            //  Define our LP token and mint an initial supply
-            let lp_tokens = ResourceBuilder::new_fungible()
-                .divisibility(DIVISIBILITY_MAXIMUM)
-                .metadata("symbol", lp_symbol)
-                .metadata("name", lp_name)
-                .mintable(rule!(require(lp_mint_badge.resource_address())), LOCKED)
-                .burnable(rule!(require(lp_mint_badge.resource_address())), LOCKED)
-                .initial_supply(tokens.amount());
-            let lp_resource_address = lp_tokens.resource_address();
-            
-            let liquidity_pool = Self {
-                pool: Vault::with_bucket(tokens),
+        /// Same as `new`, with collected fees released to the pool linearly over `vesting_epochs`
+        pub fn new_with_fee_vesting(
+            tokens: Bucket,
+            lp_symbol: String,
+            lp_name: String,
+            vesting_epochs: u64,
+        ) -> (ComponentAddress,Bucket) {
+            Self::new_with_cooldown(tokens, lp_symbol, lp_name, vesting_epochs, 0)
+        }
 
-                lp_mint_badge: Vault::with_bucket(lp_mint_badge),
-                lp_resource_address,
+        /// Same as `new_with_fee_vesting`, with liquidity removed in two steps: `request_exit` gives an exit ticket
+        /// that `redeem_exit` pays out `cooldown_epochs` later. `remove_liquidity` is disabled when `cooldown_epochs` > 0
+        pub fn new_with_cooldown(
+            tokens: Bucket,
+            lp_symbol: String,
+            lp_name: String,
+            vesting_epochs: u64,
+            cooldown_epochs: u64,
+        ) -> (ComponentAddress,Bucket) {
+            let initial_shares = tokens.amount();
+            let (pool, lp_tokens) = SingleTokenPool::new(
+                tokens,
+                initial_shares,
+                lp_name,
+                lp_symbol,
+                vesting_epochs,
+                cooldown_epochs,
+            );
 
-                lp_per_asset_ratio: Decimal::one(),
-            }
-            .instantiate()
-            .globalize();
+            let liquidity_pool = Self { pool }
+                .instantiate()
+                .globalize();
 
             // Return the new LiquidityPool component, as well as the initial supply of LP tokens
             (liquidity_pool,lp_tokens)
         }
 
         /// Adds liquidity to this pool and return the LP tokens representing pool shares
-        /// along with any remainder.
         pub fn add_liquidity(&mut self,
             tokens: Bucket
         ) -> Bucket {
-
-            // Get the resource manager of the lp tokens
-            let lp_resource_manager = borrow_resource_manager!(self.lp_resource_address);
-
-            // Mint LP tokens according to the share the provider is contributing 
-            let  supply_to_mint = tokens.amount() * self.lp_per_asset_ratio;
-
-            let lp_tokens = self.lp_mint_badge.authorize(|| {
-               return lp_resource_manager.mint(supply_to_mint);
-            });
-
-            // Add tokens to the liquidity pool
-            self.pool.put(tokens);
-
-            // Return the LP tokens along with any remainder
-            lp_tokens
+            self.pool.add_liquidity(tokens)
         }
 
-        /// Collect fee for liquidity provider. will be added to the pool and LP/Token will be ajusted accordingly
-        pub fn add_collected_fee(&mut self, 
+        /// Collect fee for liquidity provider. will be released to the pool over the vesting epochs
+        /// and LP/Token will be ajusted accordingly
+        pub fn add_collected_fee(&mut self,
             tokens: Bucket
         )  {
+            self.pool.add_fees(tokens);
+        }
 
-            self.pool.put(tokens);
-
-            let lp_resource_manager = borrow_resource_manager!(self.lp_resource_address);
-            if lp_resource_manager.total_supply() !=  Decimal::zero() {
-                self.lp_per_asset_ratio = lp_resource_manager.total_supply() / self.pool.amount();
-            } else {
-                self.lp_per_asset_ratio = Decimal::one()
-            }
+        /// Collected fees that are not released to the pool yet
+        pub fn get_locked_fees(&mut self) -> Decimal {
+            self.pool.release_fees();
+            self.pool.locked_fees()
         }
 
         /// Removes liquidity from this pool.
         pub fn remove_liquidity(&mut self,
              lp_tokens: Bucket
         ) -> Bucket {
-            assert!(
-                self.lp_resource_address == lp_tokens.resource_address(),
-                "Wrong token type passed in"
-            );
+            self.pool.remove_liquidity(lp_tokens)
+        }
 
-            // Withdraw the correct amounts of tokens A and B from reserves
-            let  to_remove = lp_tokens.amount()/(self.lp_per_asset_ratio);
+        /// Burns LP tokens for an exit ticket, redeemable after the cooldown for the tokens
+        /// the LP tokens are worth now. The tokens stop earning fees.
+        pub fn request_exit(&mut self,
+             lp_tokens: Bucket
+        ) -> Bucket {
+            self.pool.request_exit(lp_tokens)
+        }
+
+        /// Burns exit tickets whose cooldown is over and returns their tokens
+        pub fn redeem_exit(&mut self,
+             exit_tickets: Bucket
+        ) -> Bucket {
+            self.pool.redeem_exit(exit_tickets)
+        }
 
-            // Remain residual liquidity will be withdrawl on the last withdrawal  
-            let withdrawn = self.pool.take(std::cmp::min(to_remove, self.pool.amount()));
-           
-            // Burn the LP tokens received
-            self.lp_mint_badge.authorize(|| {
-                lp_tokens.burn();
-            });
+        /// Liquidity backing the LP tokens, without pending exits and locked fees
+        pub fn get_liquidity(&mut self) -> Decimal {
+            self.pool.release_fees();
+            self.pool.liquidity()
+        }
 
-            // Rest lp_per_asset_ratio if the pool is empty
-            if self.pool.amount() ==  Decimal::zero() {
-                self.lp_per_asset_ratio = Decimal::one()
-            }
+        /// Tokens owed to holders of exit tickets
+        pub fn get_pending_exits(&self) -> Decimal {
+            self.pool.pending_exits()
+        }
 
-            // Return the withdrawn tokens
-            withdrawn
+        /// Tokens one LP token is worth, released fees included
+        pub fn get_token_per_share(&mut self) -> Decimal {
+            self.pool.release_fees();
+            self.pool.token_per_share()
         }
     }
 }
@@ -0,0 +1,304 @@
+use radix_engine::ledger::*;
+use radix_engine::transaction::*;
+use scrypto::prelude::*;
+
+struct Account {
+    pk: EcdsaPublicKey,
+    sk: EcdsaPrivateKey,
+    address: ComponentAddress,
+}
+
+fn execute(executor: &mut TransactionExecutor<InMemorySubstateStore>, account: &Account, builder: &mut TransactionBuilder) -> Receipt {
+    let transaction = builder
+        .call_method_with_all_resources(account.address, "deposit_batch")
+        .build(executor.get_nonce([account.pk]))
+        .sign([&account.sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    println!("{:?}\n", receipt);
+    receipt
+}
+
+fn run(executor: &mut TransactionExecutor<InMemorySubstateStore>, account: &Account, builder: &mut TransactionBuilder) -> Receipt {
+    let receipt = execute(executor, account, builder);
+    assert!(receipt.result.is_ok());
+    receipt
+}
+
+fn balance(executor: &mut TransactionExecutor<InMemorySubstateStore>, account: &Account, resource: ResourceAddress) -> Decimal {
+    let receipt = run(executor, account, TransactionBuilder::new().call_method(account.address, "balance", args![resource]));
+    scrypto_decode(&receipt.outputs[0].raw[..]).unwrap()
+}
+
+fn try_send(
+    executor: &mut TransactionExecutor<InMemorySubstateStore>,
+    account: &Account,
+    pool: ComponentAddress,
+    method: &str,
+    amount: Decimal,
+    resource: ResourceAddress,
+) -> Receipt {
+    execute(
+        executor,
+        account,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(amount, resource, account.address)
+            .take_from_worktop(resource, |builder, bucket_id| {
+                builder.call_method(pool, method, args![scrypto::resource::Bucket(bucket_id)])
+            }),
+    )
+}
+
+fn send(
+    executor: &mut TransactionExecutor<InMemorySubstateStore>,
+    account: &Account,
+    pool: ComponentAddress,
+    method: &str,
+    amount: Decimal,
+    resource: ResourceAddress,
+) -> Receipt {
+    let receipt = try_send(executor, account, pool, method, amount, resource);
+    assert!(receipt.result.is_ok());
+    receipt
+}
+
+fn query(executor: &mut TransactionExecutor<InMemorySubstateStore>, account: &Account, pool: ComponentAddress, method: &str) -> Decimal {
+    let receipt = run(executor, account, TransactionBuilder::new().call_method(pool, method, args![]));
+    scrypto_decode(&receipt.outputs[0].raw[..]).unwrap()
+}
+
+// A pool of 1000 XRD with 1000 LP tokens (999.999999 for the owner) and fees vested over 10 epochs
+fn setup(executor: &mut TransactionExecutor<InMemorySubstateStore>) -> (Account, ComponentAddress, ResourceAddress) {
+    let (pk, sk, address) = executor.new_account();
+    let owner = Account { pk, sk, address };
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let receipt = run(
+        executor,
+        &owner,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(dec!("1000"), RADIX_TOKEN, owner.address)
+            .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+                builder.call_function(
+                    package,
+                    "LiquidityPool",
+                    "new_with_fee_vesting",
+                    args![scrypto::resource::Bucket(bucket_id), "LPT".to_string(), "LP Token".to_string(), 10u64],
+                )
+            }),
+    );
+    let pool = receipt.new_component_addresses[0];
+    let lp_token = receipt.new_resource_addresses[1];
+
+    (owner, pool, lp_token)
+}
+
+#[test]
+fn test_fees_vest_linearly() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (owner, pool, lp_token) = setup(&mut executor);
+    assert_eq!(balance(&mut executor, &owner, lp_token), dec!("999.999999"));
+
+    send(&mut executor, &owner, pool, "add_collected_fee", dec!("100"), RADIX_TOKEN);
+    assert_eq!(query(&mut executor, &owner, pool, "get_locked_fees"), dec!("100"));
+
+    executor.substate_store_mut().set_epoch(5);
+    assert_eq!(query(&mut executor, &owner, pool, "get_locked_fees"), dec!("50"));
+
+    // the only provider gets the pool and the half of the fees that vested, but for the part of the LP tokens kept by the pool
+    let before = balance(&mut executor, &owner, RADIX_TOKEN);
+    send(&mut executor, &owner, pool, "remove_liquidity", dec!("999.999999"), lp_token);
+    assert_eq!(balance(&mut executor, &owner, RADIX_TOKEN) - before, dec!("1049.99999895"));
+}
+
+#[test]
+fn test_deposit_right_before_fees_earns_nothing() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (owner, pool, lp_token) = setup(&mut executor);
+    let (pk, sk, address) = executor.new_account();
+    let sniper = Account { pk, sk, address };
+
+    send(&mut executor, &sniper, pool, "add_liquidity", dec!("1000"), RADIX_TOKEN);
+    assert_eq!(balance(&mut executor, &sniper, lp_token), dec!("1000"));
+    send(&mut executor, &owner, pool, "add_collected_fee", dec!("100"), RADIX_TOKEN);
+
+    // leaving in the same epoch returns the deposit, none of the fees have vested
+    let before = balance(&mut executor, &sniper, RADIX_TOKEN);
+    send(&mut executor, &sniper, pool, "remove_liquidity", dec!("1000"), lp_token);
+    assert_eq!(balance(&mut executor, &sniper, RADIX_TOKEN) - before, dec!("1000"));
+
+    // the remaining provider gets all the fees once they vested
+    executor.substate_store_mut().set_epoch(10);
+    let before = balance(&mut executor, &owner, RADIX_TOKEN);
+    send(&mut executor, &owner, pool, "remove_liquidity", dec!("999.999999"), lp_token);
+    assert_eq!(balance(&mut executor, &owner, RADIX_TOKEN) - before, dec!("1099.9999989"));
+}
+
+#[test]
+fn test_no_vesting() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (pk, sk, address) = executor.new_account();
+    let owner = Account { pk, sk, address };
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let receipt = run(
+        &mut executor,
+        &owner,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(dec!("1000"), RADIX_TOKEN, owner.address)
+            .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+                builder.call_function(
+                    package,
+                    "LiquidityPool",
+                    "new_with_fee_vesting",
+                    args![scrypto::resource::Bucket(bucket_id), "LPT".to_string(), "LP Token".to_string(), 0u64],
+                )
+            }),
+    );
+    let pool = receipt.new_component_addresses[0];
+    let lp_token = receipt.new_resource_addresses[1];
+
+    // with 0 vesting epochs fees are credited right away, like before
+    send(&mut executor, &owner, pool, "add_collected_fee", dec!("100"), RADIX_TOKEN);
+    assert_eq!(query(&mut executor, &owner, pool, "get_locked_fees"), dec!("0"));
+    let before = balance(&mut executor, &owner, RADIX_TOKEN);
+    send(&mut executor, &owner, pool, "remove_liquidity", dec!("999.999999"), lp_token);
+    assert_eq!(balance(&mut executor, &owner, RADIX_TOKEN) - before, dec!("1099.9999989"));
+}
+
+#[test]
+fn test_exit_after_cooldown() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (pk, sk, address) = executor.new_account();
+    let owner = Account { pk, sk, address };
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    // exits are redeemable 5 epochs after they are requested
+    let receipt = run(
+        &mut executor,
+        &owner,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(dec!("1000"), RADIX_TOKEN, owner.address)
+            .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+                builder.call_function(
+                    package,
+                    "LiquidityPool",
+                    "new_with_cooldown",
+                    args![scrypto::resource::Bucket(bucket_id), "LPT".to_string(), "LP Token".to_string(), 0u64, 5u64],
+                )
+            }),
+    );
+    let pool = receipt.new_component_addresses[0];
+    let lp_token = receipt.new_resource_addresses[1];
+    let exit_ticket = receipt.new_resource_addresses[2];
+
+    let receipt = try_send(&mut executor, &owner, pool, "remove_liquidity", dec!("500"), lp_token);
+    assert!(!receipt.result.is_ok());
+
+    executor.substate_store_mut().set_epoch(1);
+    send(&mut executor, &owner, pool, "request_exit", dec!("500"), lp_token);
+    assert_eq!(balance(&mut executor, &owner, exit_ticket), dec!("1"));
+    assert_eq!(query(&mut executor, &owner, pool, "get_liquidity"), dec!("500"));
+    assert_eq!(query(&mut executor, &owner, pool, "get_pending_exits"), dec!("500"));
+
+    // pending exits don't earn fees anymore
+    send(&mut executor, &owner, pool, "add_collected_fee", dec!("100"), RADIX_TOKEN);
+
+    executor.substate_store_mut().set_epoch(5);
+    let receipt = try_send(&mut executor, &owner, pool, "redeem_exit", dec!("1"), exit_ticket);
+    assert!(!receipt.result.is_ok());
+
+    executor.substate_store_mut().set_epoch(6);
+    let before = balance(&mut executor, &owner, RADIX_TOKEN);
+    send(&mut executor, &owner, pool, "redeem_exit", dec!("1"), exit_ticket);
+    assert_eq!(balance(&mut executor, &owner, RADIX_TOKEN) - before, dec!("500"));
+    assert_eq!(balance(&mut executor, &owner, exit_ticket), dec!("0"));
+    assert_eq!(query(&mut executor, &owner, pool, "get_pending_exits"), dec!("0"));
+}
+
+#[test]
+fn test_first_depositor_cannot_inflate_lp_token() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (pk, sk, address) = executor.new_account();
+    let attacker = Account { pk, sk, address };
+    let (pk, sk, address) = executor.new_account();
+    let victim = Account { pk, sk, address };
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    // the attacker creates a pool as small as allowed and donates fees to make one LP token worth a lot
+    let receipt = run(
+        &mut executor,
+        &attacker,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(dec!("0.000002"), RADIX_TOKEN, attacker.address)
+            .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+                builder.call_function(
+                    package,
+                    "LiquidityPool",
+                    "new_with_fee_vesting",
+                    args![scrypto::resource::Bucket(bucket_id), "LPT".to_string(), "LP Token".to_string(), 0u64],
+                )
+            }),
+    );
+    let pool = receipt.new_component_addresses[0];
+    let lp_token = receipt.new_resource_addresses[1];
+    send(&mut executor, &attacker, pool, "add_collected_fee", dec!("1000"), RADIX_TOKEN);
+
+    // a later deposit still mints LP tokens, and half of the donation belongs to the LP tokens kept by the pool
+    send(&mut executor, &victim, pool, "add_liquidity", dec!("10"), RADIX_TOKEN);
+    let victim_lp = balance(&mut executor, &victim, lp_token);
+    assert!(victim_lp > dec!("0"));
+    let before = balance(&mut executor, &victim, RADIX_TOKEN);
+    send(&mut executor, &victim, pool, "remove_liquidity", victim_lp, lp_token);
+    let returned = balance(&mut executor, &victim, RADIX_TOKEN) - before;
+    assert!(dec!("10") - returned < dec!("0.000001"), "the victim got back {}", returned);
+
+    let before = balance(&mut executor, &attacker, RADIX_TOKEN);
+    send(&mut executor, &attacker, pool, "remove_liquidity", dec!("0.000001"), lp_token);
+    let returned = balance(&mut executor, &attacker, RADIX_TOKEN) - before;
+    assert!(returned < dec!("500.000002"), "the attacker got back {}", returned);
+}
+
+#[test]
+fn test_late_depositor_only_gets_fees_vested_after_deposit() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (owner, pool, lp_token) = setup(&mut executor);
+    let (pk, sk, address) = executor.new_account();
+    let alice = Account { pk, sk, address };
+    let (pk, sk, address) = executor.new_account();
+    let bob = Account { pk, sk, address };
+
+    send(&mut executor, &alice, pool, "add_liquidity", dec!("1000"), RADIX_TOKEN);
+    send(&mut executor, &owner, pool, "add_collected_fee", dec!("200"), RADIX_TOKEN);
+
+    // bob comes in once half of the fees vested, at 2100 XRD for 2000 LP tokens
+    executor.substate_store_mut().set_epoch(5);
+    send(&mut executor, &bob, pool, "add_liquidity", dec!("1100"), RADIX_TOKEN);
+    assert_eq!(query(&mut executor, &owner, pool, "get_liquidity"), dec!("3200"));
+
+    // everyone leaves once all fees vested.
+    // Alice is owed 1000 + 50 + 100 * 1000 / 3047.6 = 1082.8125 and bob 1100 + 100 * 1047.6 / 3047.6 = 1134.375,
+    // rounding only ever goes to the pool
+    executor.substate_store_mut().set_epoch(10);
+    let alice_lp = balance(&mut executor, &alice, lp_token);
+    let before = balance(&mut executor, &alice, RADIX_TOKEN);
+    send(&mut executor, &alice, pool, "remove_liquidity", alice_lp, lp_token);
+    let alice_gain = balance(&mut executor, &alice, RADIX_TOKEN) - before;
+    assert!(alice_gain <= dec!("1082.8125") && alice_gain > dec!("1082.8124"), "alice got {}", alice_gain);
+
+    let bob_lp = balance(&mut executor, &bob, lp_token);
+    let before = balance(&mut executor, &bob, RADIX_TOKEN);
+    send(&mut executor, &bob, pool, "remove_liquidity", bob_lp, lp_token);
+    let bob_gain = balance(&mut executor, &bob, RADIX_TOKEN) - before;
+    assert!(bob_gain <= dec!("1134.375") && bob_gain > dec!("1134.3749"), "bob got {}", bob_gain);
+
+    // what is left is the owner's part and the value of the LP tokens kept by the pool
+    send(&mut executor, &owner, pool, "remove_liquidity", dec!("999.999999"), lp_token);
+    let left = query(&mut executor, &owner, pool, "get_liquidity");
+    assert!(left > dec!("0") && left < dec!("0.000002"), "{} left in the pool", left);
+}
@@ -1,82 +1,94 @@
 use scrypto::prelude::*;
+use single_token_pool::{SingleTokenPool, DEFAULT_VESTING_EPOCHS};
 
 /*
  * Liquidity pool with a single token and fees collection mechanism.
- * This component is not meant to be used by itself. 
+ * This component is not meant to be used by itself.
  * You should use it inside another component.
+ * Fees are released to the liquidity providers linearly over `vesting_epochs`.
+ * With a cooldown, liquidity is removed in two steps: `request_exit` and `redeem_exit`.
+ * The accounting is done by `SingleTokenPool` from the `single_token_pool` package.
  */
+
 blueprint! {
     struct SingleTokenLiquidityPool {
-        pool: Vault,
-        fees: Vault,
-        lp_minter_badge: Vault,
-        lp_token: ResourceAddress
+        pool: SingleTokenPool,
+        // The initial LP tokens are not handed out, so the initial funds stay in the pool
+        initial_lp_tokens: Vault
     }
 
     impl SingleTokenLiquidityPool {
         pub fn new(initial_funds: Bucket, lp_initial_supply: Decimal) -> ComponentAddress {
-            let funds_resource_def = initial_funds.resource_address();
-
-            // Create badge that will be used to mint and burn LP tokens
-            let lp_minter: Bucket = ResourceBuilder::new_fungible()
-                .divisibility(DIVISIBILITY_NONE)
-                .initial_supply(1);
+            Self::new_with_fee_vesting(initial_funds, lp_initial_supply, DEFAULT_VESTING_EPOCHS)
+        }
 
-            // Create the LP token definition
-            let mut lp_token = ResourceBuilder::new_fungible()
-                .metadata("name", "LP Token")
-                .metadata("symbol", "LP")
-                .mintable(rule!(require(lp_minter.resource_address())), LOCKED)
-                .burnable(rule!(require(lp_minter.resource_address())), LOCKED)
-                .no_initial_supply();
+        pub fn new_with_fee_vesting(initial_funds: Bucket, lp_initial_supply: Decimal, vesting_epochs: u64) -> ComponentAddress {
+            Self::new_with_cooldown(initial_funds, lp_initial_supply, vesting_epochs, 0)
+        }
 
-            // Mint initial LP tokens
-            lp_minter.authorize(|| {
-                borrow_resource_manager!(lp_token).mint(lp_initial_supply);
-            });
+        // With a cooldown, `remove_liquidity` is disabled and exits are redeemable `cooldown_epochs` after `request_exit`
+        pub fn new_with_cooldown(
+            initial_funds: Bucket,
+            lp_initial_supply: Decimal,
+            vesting_epochs: u64,
+            cooldown_epochs: u64
+        ) -> ComponentAddress {
+            let (pool, lp_tokens) = SingleTokenPool::new(
+                initial_funds,
+                lp_initial_supply,
+                "LP Token".to_string(),
+                "LP".to_string(),
+                vesting_epochs,
+                cooldown_epochs
+            );
 
             Self {
-                pool: Vault::with_bucket(initial_funds),
-                fees: Vault::new(funds_resource_def),
-                lp_minter_badge: Vault::with_bucket(lp_minter),
-                lp_token: lp_token
-
+                pool: pool,
+                initial_lp_tokens: Vault::with_bucket(lp_tokens)
             }
             .instantiate().globalize()
         }
 
         // Will be called by other components
         pub fn add_fees(&mut self, fees: Bucket) {
-            self.fees.put(fees);
+            self.pool.add_fees(fees);
         }
 
         // Contribute tokens to the pool in exchange of an LP token
         pub fn add_liquidity(&mut self, liquidity: Bucket) -> Bucket {
-            let pool_share = liquidity.amount() / self.pool.amount();
-            self.pool.put(liquidity);
-
-            // Return newly minted lp_tokens
-            self.lp_minter_badge.authorize(|| {
-                borrow_resource_manager!(self.lp_token)
-                    .mint(borrow_resource_manager!(self.lp_token).total_supply() * pool_share)
-            })
+            self.pool.add_liquidity(liquidity)
         }
 
         // Give LP token back to get portion of the pool and fees
         pub fn remove_liquidity(&mut self, lp_tokens: Bucket) -> Bucket {
-            assert!(lp_tokens.resource_address() == self.lp_token, "Wrong LP token !");
+            self.pool.remove_liquidity(lp_tokens)
+        }
 
-            let share = lp_tokens.amount() / borrow_resource_manager!(self.lp_token).total_supply();
-            
-            // Burn the provided LP token
-            self.lp_minter_badge.authorize(|| {
-                lp_tokens.burn();
-            });
+        // Give LP token back for an exit ticket, redeemable for the current portion of the pool and fees after the cooldown
+        pub fn request_exit(&mut self, lp_tokens: Bucket) -> Bucket {
+            self.pool.request_exit(lp_tokens)
+        }
+
+        // Burn an exit ticket once its cooldown is over and get the tokens
+        pub fn redeem_exit(&mut self, exit_ticket: Bucket) -> Bucket {
+            self.pool.redeem_exit(exit_ticket)
+        }
+
+        // Liquidity backing the LP tokens, released fees included, not including locked fees and pending exits
+        pub fn get_liquidity(&mut self) -> Decimal {
+            self.pool.release_fees();
+            self.pool.liquidity()
+        }
+
+        // Tokens owed to holders of exit tickets
+        pub fn get_pending_exits(&self) -> Decimal {
+            self.pool.pending_exits()
+        }
 
-            // Return the share of fees and pool
-            let mut return_bucket = self.fees.take(self.fees.amount() * share);
-            return_bucket.put(self.pool.take(self.pool.amount() * share));
-            return_bucket
+        // Fees that are added but not released to the liquidity providers yet
+        pub fn get_locked_fees(&mut self) -> Decimal {
+            self.pool.release_fees();
+            self.pool.locked_fees()
         }
     }
 }
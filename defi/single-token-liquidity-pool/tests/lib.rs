@@ -0,0 +1,246 @@
+use radix_engine::ledger::*;
+use radix_engine::transaction::*;
+use scrypto::prelude::*;
+
+struct Account {
+    pk: EcdsaPublicKey,
+    sk: EcdsaPrivateKey,
+    address: ComponentAddress,
+}
+
+fn execute(executor: &mut TransactionExecutor<InMemorySubstateStore>, account: &Account, builder: &mut TransactionBuilder) -> Receipt {
+    let transaction = builder
+        .call_method_with_all_resources(account.address, "deposit_batch")
+        .build(executor.get_nonce([account.pk]))
+        .sign([&account.sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    println!("{:?}\n", receipt);
+    receipt
+}
+
+fn run(executor: &mut TransactionExecutor<InMemorySubstateStore>, account: &Account, builder: &mut TransactionBuilder) -> Receipt {
+    let receipt = execute(executor, account, builder);
+    assert!(receipt.result.is_ok());
+    receipt
+}
+
+fn balance(executor: &mut TransactionExecutor<InMemorySubstateStore>, account: &Account, resource: ResourceAddress) -> Decimal {
+    let receipt = run(executor, account, TransactionBuilder::new().call_method(account.address, "balance", args![resource]));
+    scrypto_decode(&receipt.outputs[0].raw[..]).unwrap()
+}
+
+fn try_send(
+    executor: &mut TransactionExecutor<InMemorySubstateStore>,
+    account: &Account,
+    pool: ComponentAddress,
+    method: &str,
+    amount: Decimal,
+    resource: ResourceAddress,
+) -> Receipt {
+    execute(
+        executor,
+        account,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(amount, resource, account.address)
+            .take_from_worktop(resource, |builder, bucket_id| {
+                builder.call_method(pool, method, args![scrypto::resource::Bucket(bucket_id)])
+            }),
+    )
+}
+
+fn send(
+    executor: &mut TransactionExecutor<InMemorySubstateStore>,
+    account: &Account,
+    pool: ComponentAddress,
+    method: &str,
+    amount: Decimal,
+    resource: ResourceAddress,
+) -> Receipt {
+    let receipt = try_send(executor, account, pool, method, amount, resource);
+    assert!(receipt.result.is_ok());
+    receipt
+}
+
+fn query(executor: &mut TransactionExecutor<InMemorySubstateStore>, account: &Account, pool: ComponentAddress, method: &str) -> Decimal {
+    let receipt = run(executor, account, TransactionBuilder::new().call_method(pool, method, args![]));
+    scrypto_decode(&receipt.outputs[0].raw[..]).unwrap()
+}
+
+fn new_account(executor: &mut TransactionExecutor<InMemorySubstateStore>) -> Account {
+    let (pk, sk, address) = executor.new_account();
+    Account { pk, sk, address }
+}
+
+// A pool created with `new_with_cooldown`, the initial LP tokens stay in the component.
+// Returns the pool, the LP token and the exit ticket
+fn create_pool(
+    executor: &mut TransactionExecutor<InMemorySubstateStore>,
+    owner: &Account,
+    initial_funds: Decimal,
+    lp_initial_supply: Decimal,
+    vesting_epochs: u64,
+    cooldown_epochs: u64,
+) -> (ComponentAddress, ResourceAddress, ResourceAddress) {
+    let package = executor.publish_package(compile_package!()).unwrap();
+    let receipt = run(
+        executor,
+        owner,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(initial_funds, RADIX_TOKEN, owner.address)
+            .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+                builder.call_function(
+                    package,
+                    "SingleTokenLiquidityPool",
+                    "new_with_cooldown",
+                    args![scrypto::resource::Bucket(bucket_id), lp_initial_supply, vesting_epochs, cooldown_epochs],
+                )
+            }),
+    );
+    // minter badge, LP token and exit ticket resources
+    (receipt.new_component_addresses[0], receipt.new_resource_addresses[1], receipt.new_resource_addresses[2])
+}
+
+// A pool of 1000 XRD with 1000 LP tokens kept by the component and fees vested over `vesting_epochs`,
+// and a provider who added 1000 XRD for 1000 LP tokens
+fn setup(executor: &mut TransactionExecutor<InMemorySubstateStore>, vesting_epochs: u64) -> (Account, Account, ComponentAddress, ResourceAddress) {
+    let owner = new_account(executor);
+    let provider = new_account(executor);
+    let (pool, lp_token, _) = create_pool(executor, &owner, dec!("1000"), dec!("1000"), vesting_epochs, 0);
+
+    send(executor, &provider, pool, "add_liquidity", dec!("1000"), RADIX_TOKEN);
+    assert_eq!(balance(executor, &provider, lp_token), dec!("1000"));
+
+    (owner, provider, pool, lp_token)
+}
+
+#[test]
+fn test_fees_vest_linearly() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (owner, provider, pool, lp_token) = setup(&mut executor, 10);
+
+    send(&mut executor, &owner, pool, "add_fees", dec!("100"), RADIX_TOKEN);
+    assert_eq!(query(&mut executor, &owner, pool, "get_locked_fees"), dec!("100"));
+
+    executor.substate_store_mut().set_epoch(5);
+    assert_eq!(query(&mut executor, &owner, pool, "get_locked_fees"), dec!("50"));
+
+    // the provider holds half of the LP tokens, so gets half of the fees that vested
+    let before = balance(&mut executor, &provider, RADIX_TOKEN);
+    send(&mut executor, &provider, pool, "remove_liquidity", dec!("1000"), lp_token);
+    assert_eq!(balance(&mut executor, &provider, RADIX_TOKEN) - before, dec!("1025"));
+}
+
+#[test]
+fn test_deposit_right_before_fees_earns_nothing() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (owner, sniper, pool, lp_token) = setup(&mut executor, 10);
+
+    send(&mut executor, &owner, pool, "add_fees", dec!("100"), RADIX_TOKEN);
+
+    // leaving in the same epoch returns the deposit, none of the fees have vested
+    let before = balance(&mut executor, &sniper, RADIX_TOKEN);
+    send(&mut executor, &sniper, pool, "remove_liquidity", dec!("1000"), lp_token);
+    assert_eq!(balance(&mut executor, &sniper, RADIX_TOKEN) - before, dec!("1000"));
+
+    // all the fees go to the initial LP tokens once they vested
+    executor.substate_store_mut().set_epoch(10);
+    assert_eq!(query(&mut executor, &owner, pool, "get_liquidity"), dec!("1100"));
+}
+
+#[test]
+fn test_dust_fees_do_not_delay_earlier_fees() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (owner, provider, pool, lp_token) = setup(&mut executor, 10);
+    let griefer = new_account(&mut executor);
+
+    send(&mut executor, &owner, pool, "add_fees", dec!("100"), RADIX_TOKEN);
+    for epoch in 1..10 {
+        executor.substate_store_mut().set_epoch(epoch);
+        send(&mut executor, &griefer, pool, "add_fees", dec!("0.000000000000000001"), RADIX_TOKEN);
+    }
+
+    // the 100 XRD vested at epoch 10 all the same, only some of the dust is still locked
+    executor.substate_store_mut().set_epoch(10);
+    assert!(query(&mut executor, &owner, pool, "get_locked_fees") < dec!("0.00000000000000001"));
+    let before = balance(&mut executor, &provider, RADIX_TOKEN);
+    send(&mut executor, &provider, pool, "remove_liquidity", dec!("1000"), lp_token);
+    let gain = balance(&mut executor, &provider, RADIX_TOKEN) - before;
+    assert!(gain >= dec!("1050") && gain < dec!("1050.000001"), "provider got {}", gain);
+}
+
+#[test]
+fn test_no_vesting() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (owner, provider, pool, lp_token) = setup(&mut executor, 0);
+
+    // with 0 vesting epochs fees are credited right away
+    send(&mut executor, &owner, pool, "add_fees", dec!("100"), RADIX_TOKEN);
+    assert_eq!(query(&mut executor, &owner, pool, "get_locked_fees"), dec!("0"));
+    let before = balance(&mut executor, &provider, RADIX_TOKEN);
+    send(&mut executor, &provider, pool, "remove_liquidity", dec!("1000"), lp_token);
+    assert_eq!(balance(&mut executor, &provider, RADIX_TOKEN) - before, dec!("1050"));
+}
+
+#[test]
+fn test_exit_after_cooldown() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let owner = new_account(&mut executor);
+    let provider = new_account(&mut executor);
+
+    // exits are redeemable 5 epochs after they are requested
+    let (pool, lp_token, exit_ticket) = create_pool(&mut executor, &owner, dec!("1000"), dec!("1000"), 0, 5);
+    send(&mut executor, &provider, pool, "add_liquidity", dec!("1000"), RADIX_TOKEN);
+
+    let receipt = try_send(&mut executor, &provider, pool, "remove_liquidity", dec!("500"), lp_token);
+    assert!(!receipt.result.is_ok());
+
+    executor.substate_store_mut().set_epoch(1);
+    send(&mut executor, &provider, pool, "request_exit", dec!("500"), lp_token);
+    assert_eq!(balance(&mut executor, &provider, exit_ticket), dec!("1"));
+    assert_eq!(query(&mut executor, &provider, pool, "get_liquidity"), dec!("1500"));
+    assert_eq!(query(&mut executor, &provider, pool, "get_pending_exits"), dec!("500"));
+
+    // pending exits don't earn fees anymore
+    send(&mut executor, &owner, pool, "add_fees", dec!("100"), RADIX_TOKEN);
+    assert_eq!(query(&mut executor, &provider, pool, "get_liquidity"), dec!("1600"));
+
+    executor.substate_store_mut().set_epoch(5);
+    let receipt = try_send(&mut executor, &provider, pool, "redeem_exit", dec!("1"), exit_ticket);
+    assert!(!receipt.result.is_ok());
+
+    executor.substate_store_mut().set_epoch(6);
+    let before = balance(&mut executor, &provider, RADIX_TOKEN);
+    send(&mut executor, &provider, pool, "redeem_exit", dec!("1"), exit_ticket);
+    assert_eq!(balance(&mut executor, &provider, RADIX_TOKEN) - before, dec!("500"));
+    assert_eq!(balance(&mut executor, &provider, exit_ticket), dec!("0"));
+    assert_eq!(query(&mut executor, &provider, pool, "get_pending_exits"), dec!("0"));
+}
+
+#[test]
+fn test_first_depositor_cannot_inflate_lp_token() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let attacker = new_account(&mut executor);
+    let victim = new_account(&mut executor);
+
+    // the attacker creates a pool as small as allowed and donates fees to make one LP token worth a lot
+    let (pool, lp_token, _) = create_pool(&mut executor, &attacker, dec!("0.000002"), dec!("0.000002"), 0, 0);
+    send(&mut executor, &attacker, pool, "add_fees", dec!("1000"), RADIX_TOKEN);
+
+    // a later deposit still mints LP tokens and can be withdrawn almost entirely
+    send(&mut executor, &victim, pool, "add_liquidity", dec!("10"), RADIX_TOKEN);
+    let victim_lp = balance(&mut executor, &victim, lp_token);
+    assert!(victim_lp > dec!("0"));
+    let before = balance(&mut executor, &victim, RADIX_TOKEN);
+    send(&mut executor, &victim, pool, "remove_liquidity", victim_lp, lp_token);
+    let returned = balance(&mut executor, &victim, RADIX_TOKEN) - before;
+    assert!(dec!("10") - returned < dec!("0.000001"), "the victim got back {}", returned);
+
+    // the initial LP tokens stay in the component, the attacker can't take the donation back
+    assert!(query(&mut executor, &attacker, pool, "get_liquidity") >= dec!("1000.000002"));
+}
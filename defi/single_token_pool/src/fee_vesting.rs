@@ -0,0 +1,125 @@
+use sbor::*;
+use scrypto::prelude::*;
+
+/// Used by the pools' `new` functions
+pub const DEFAULT_VESTING_EPOCHS: u64 = 10;
+
+/// Linear vesting of collected fees, stored in a pool's state.
+///
+/// Each addition vests on its own over `vesting_epochs`, so new fees never push back the release of older ones.
+/// Additions of the same epoch are merged, and fully released ones are dropped, so there are at most
+/// `vesting_epochs + 1` of them.
+///
+/// All methods take the current epoch so the accounting can be tested without a ledger.
+#[derive(TypeId, Encode, Decode, Describe, Debug, Clone, PartialEq)]
+pub struct FeeVesting {
+    vesting_epochs: u64,
+    // (fees not released yet, epoch at which they are all released) of each addition, oldest first
+    tranches: Vec<(Decimal, u64)>,
+    // epoch of the last release
+    last_release_epoch: u64,
+}
+
+impl FeeVesting {
+    pub fn new(vesting_epochs: u64, current_epoch: u64) -> Self {
+        Self {
+            vesting_epochs,
+            tranches: Vec::new(),
+            last_release_epoch: current_epoch,
+        }
+    }
+
+    /// Releases what vested since the last call and returns that amount.
+    pub fn release(&mut self, current_epoch: u64) -> Decimal {
+        let mut released = Decimal::zero();
+        for (locked, end_epoch) in self.tranches.iter_mut() {
+            let amount = if current_epoch >= *end_epoch {
+                *locked
+            } else if current_epoch > self.last_release_epoch {
+                *locked * Decimal::from(current_epoch - self.last_release_epoch)
+                    / Decimal::from(*end_epoch - self.last_release_epoch)
+            } else {
+                Decimal::zero()
+            };
+            *locked = *locked - amount;
+            released = released + amount;
+        }
+
+        self.tranches.retain(|(locked, _)| !locked.is_zero());
+        self.last_release_epoch = current_epoch;
+        released
+    }
+
+    /// Adds fees to vest over `vesting_epochs` from now, returns what was released before adding them (see `release`).
+    pub fn add(&mut self, amount: Decimal, current_epoch: u64) -> Decimal {
+        assert!(!amount.is_negative(), "Cannot add negative fees");
+        let released = self.release(current_epoch);
+        if amount.is_zero() {
+            return released;
+        }
+
+        let end_epoch = current_epoch + self.vesting_epochs;
+        match self.tranches.last_mut() {
+            Some((locked, last_end_epoch)) if *last_end_epoch == end_epoch => *locked = *locked + amount,
+            _ => self.tranches.push((amount, end_epoch)),
+        }
+        released
+    }
+
+    pub fn locked(&self) -> Decimal {
+        self.tranches.iter().fold(Decimal::zero(), |locked, (amount, _)| locked + *amount)
+    }
+
+    pub fn vesting_epochs(&self) -> u64 {
+        self.vesting_epochs
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_linear_release() {
+        let mut vesting = FeeVesting::new(10, 100);
+        assert_eq!(vesting.add(100.into(), 100), Decimal::zero());
+        assert_eq!(vesting.release(100), Decimal::zero());
+        assert_eq!(vesting.release(103), 30.into());
+        assert_eq!(vesting.release(103), Decimal::zero());
+        assert_eq!(vesting.release(108), 50.into());
+        assert_eq!(vesting.release(200), 20.into());
+        assert_eq!(vesting.locked(), Decimal::zero());
+    }
+
+    #[test]
+    fn test_additions_vest_separately() {
+        let mut vesting = FeeVesting::new(10, 0);
+        vesting.add(100.into(), 0);
+        // 50 vested, the other 50 still vest until epoch 10 and the new 100 until epoch 15
+        assert_eq!(vesting.add(100.into(), 5), 50.into());
+        assert_eq!(vesting.locked(), 150.into());
+        assert_eq!(vesting.release(10), 100.into());
+        assert_eq!(vesting.release(15), 50.into());
+        assert_eq!(vesting.locked(), Decimal::zero());
+    }
+
+    #[test]
+    fn test_dust_does_not_delay_earlier_fees() {
+        let mut vesting = FeeVesting::new(10, 0);
+        vesting.add(100.into(), 0);
+        let mut released = Decimal::zero();
+        for epoch in 1..=10 {
+            released = released + vesting.add(Decimal(1), epoch);
+        }
+        // the 100 vested on time, only part of the dust is still locked
+        assert!(released >= 100.into());
+        assert!(vesting.locked() <= Decimal(10));
+    }
+
+    #[test]
+    fn test_no_vesting() {
+        let mut vesting = FeeVesting::new(0, 0);
+        vesting.add(100.into(), 0);
+        assert_eq!(vesting.release(0), 100.into());
+    }
+}
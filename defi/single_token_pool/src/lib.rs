@@ -0,0 +1,7 @@
+//! The implementation behind `SingleTokenLiquidityPool` and `LiquidityPool`, both blueprints only forward to it.
+
+mod fee_vesting;
+mod pool;
+
+pub use fee_vesting::{FeeVesting, DEFAULT_VESTING_EPOCHS};
+pub use pool::{shares_for_deposit, tokens_for_shares, ExitTicket, SingleTokenPool, MINIMUM_LIQUIDITY};
@@ -1,26 +1,76 @@
 use scrypto::prelude::*;
 
+mod schedule;
+
+pub use schedule::Schedule;
+
+/// Data of a TL badge, one per lock
+#[derive(NonFungibleData)]
+pub struct TimeLockData {
+    /// Locked resource
+    resource: ResourceAddress,
+    /// Locked amount, fee excluded
+    amount: Decimal,
+    /// Epoch of the lock
+    start_epoch: u64,
+    /// Everything is unlocked at this epoch (after it with `Schedule::AtEnd`)
+    unlock_epoch: u64,
+    schedule: Schedule,
+    /// Account given as creator by the caller of `lock`, not authenticated so it is only informational.
+    /// The badge itself can be held by anyone
+    creator: ComponentAddress,
+    /// Locked amount not released yet
+    #[scrypto(mutable)]
+    remaining: Decimal,
+}
+
+impl TimeLockData {
+    /// Unlocked at `epoch` and not released yet, in amounts of `divisibility` so the last release takes the remainder
+    fn releasable(&self, epoch: u64, divisibility: u8) -> Decimal {
+        let unlocked = self.schedule.unlocked(self.amount, divisibility, self.start_epoch, self.unlock_epoch, epoch);
+        unlocked - (self.amount - self.remaining)
+    }
+
+    /// Penalty for releasing everything at `epoch`, charged on the part that is still locked.
+    /// It shrinks linearly with the remaining lock time and is rounded down to `divisibility`
+    fn early_release_penalty(&self, epoch: u64, penalty_percent: Decimal, divisibility: u8) -> Decimal {
+        let still_locked = self.remaining - self.releasable(epoch, divisibility);
+        // with `AtEnd` nothing is unlocked at the unlock epoch yet, so the penalty only drops to 0 after it
+        let end_epoch = if self.schedule == Schedule::AtEnd { self.unlock_epoch + 1 } else { self.unlock_epoch };
+        let remaining_epochs = end_epoch.saturating_sub(epoch);
+        let duration = end_epoch - self.start_epoch;
+        let penalty = still_locked * penalty_percent / dec!("100") * Decimal::from(remaining_epochs) / Decimal::from(duration);
+        round_down(penalty, divisibility)
+    }
+}
 
 blueprint! {
     struct TimeLock {
         /// Mint authorization to TL badges.
         tl_minter_vault: Vault,
         tl_minter_badge: ResourceAddress,
-        // Minted badges
-        minted: HashMap<ResourceAddress, (Decimal, u64)>,
+        // TL badges, one NFT per lock
+        tl_badge: ResourceAddress,
+        // Id of the next TL badge
+        lock_count: u64,
 
-        // Collected fees in XRD.
-        collected_fees: Vault,
-        
-        // Locked XRD
-        locked_xrd: Vault,
+        // Collected fees, per locked resource.
+        collected_fees: HashMap<ResourceAddress, Vault>,
+
+        // Locked tokens, per resource
+        locked: HashMap<ResourceAddress, Vault>,
 
         // fee in percents
         fee_percent: Decimal,
+        // (minimum duration, fee in percents) by increasing duration, replaces `fee_percent` for locks at least that long
+        fee_tiers: Vec<(u64, Decimal)>,
+        // penalty in percents of the still locked amount for a release right after the lock, scaled down to 0 at the unlock epoch.
+        // None when locks cannot be released early
+        early_release_penalty: Option<Decimal>,
     }
 
     impl TimeLock {
-       
+
         pub fn new(fee: Decimal) -> (ComponentAddress, Bucket) {
 
             let mut tl_minter_bucket = ResourceBuilder::new_fungible()
@@ -31,98 +81,290 @@ blueprint! {
             let tl_minter_resource_def = tl_minter_bucket.resource_address();
             let tl_minter_return_bucket: Bucket = tl_minter_bucket.take(1); // Return this badge to the caller
 
+            let tl_badge = ResourceBuilder::new_non_fungible()
+                .metadata("name", "Time lock badge")
+                .mintable(rule!(require(tl_minter_resource_def)), LOCKED)
+                .burnable(rule!(require(tl_minter_resource_def)), LOCKED)
+                .updateable_non_fungible_data(rule!(require(tl_minter_resource_def)), LOCKED)
+                .no_initial_supply();
+
             let access_rules = AccessRules::new()
                 .method("claim", rule!(require(tl_minter_bucket.resource_address())))
+                .method("set_fee_tiers", rule!(require(tl_minter_bucket.resource_address())))
+                .method("set_early_release_penalty", rule!(require(tl_minter_bucket.resource_address())))
                 .default(rule!(allow_all));
 
             // Instantiate the Time Lock component.
             let component = Self {
                 tl_minter_vault: Vault::with_bucket(tl_minter_bucket),
                 tl_minter_badge: tl_minter_resource_def,
-                minted: HashMap::new(),
-                collected_fees: Vault::new(RADIX_TOKEN),
-                locked_xrd: Vault::new(RADIX_TOKEN),
-                fee_percent: fee
+                tl_badge: tl_badge,
+                lock_count: 0,
+                collected_fees: HashMap::new(),
+                locked: HashMap::new(),
+                fee_percent: fee,
+                fee_tiers: Vec::new(),
+                early_release_penalty: None
             }
             .instantiate();
 
             (component.add_access_check(access_rules).globalize(), tl_minter_return_bucket)
         }
 
-        /// Lock XRD for a certain time.
-        pub fn lock(&mut self, mut lock_tokens: Bucket, duration: u64) -> Bucket{
+        /// Lock fungible tokens for a certain time, `creator` is recorded in the TL badge.
+        /// Nothing checks that the caller controls `creator`, don't rely on it for authorization
+        pub fn lock(&mut self, lock_tokens: Bucket, duration: u64, creator: ComponentAddress) -> Bucket{
+            self.lock_with_schedule(lock_tokens, duration, creator, Schedule::AtEnd)
+        }
+
+        /// Same as `lock`, with the tokens unlocking gradually over the duration
+        pub fn lock_with_schedule(
+            &mut self,
+            mut lock_tokens: Bucket,
+            duration: u64,
+            creator: ComponentAddress,
+            schedule: Schedule
+        ) -> Bucket {
 
+            let resource = lock_tokens.resource_address();
             let amount = lock_tokens.amount();
 
+            assert!(
+                matches!(borrow_resource_manager!(resource).resource_type(), ResourceType::Fungible { .. }),
+                "You can only lock fungible tokens"
+            );
             assert!(amount != Decimal::zero(), "You cannot lock zero amount");
             assert!(duration != 0, "You cannot lock with a zero duration");
+            schedule.assert_valid(duration);
 
-            
             // Setup the end time.
-            let end_time = Runtime::current_epoch() + duration;
+            let start_time = Runtime::current_epoch();
+            let end_time = start_time + duration;
 
             // fees calculation
-            let fee_amount = amount * self.fee_percent/ dec!("100");
+            let fee_amount = round_down(amount * self.get_fee_percent(duration)/ dec!("100"), divisibility(resource));
             // setup fees to be taken from the payment
             let fee_tokens = lock_tokens.take(fee_amount);
 
             let available = lock_tokens.amount();
-            
-            // Put fees in collected XRD.
-            self.collected_fees.put(fee_tokens);
 
-            // Mint TL badge with locked amount and end epoch as metadata
-            let mut tl_resource_def = ResourceBuilder::new_fungible()
-                .metadata("name", "Time lock badge")
-                .metadata("amount", available.to_string())
-                .metadata("ends", end_time.to_string())
-                .mintable(rule!(require(self.tl_minter_vault.resource_address())), LOCKED)
-                .burnable(rule!(require(self.tl_minter_vault.resource_address())), LOCKED)
-                .no_initial_supply();
+            // Put fees in collected fees of that resource.
+            self.collected_fees.entry(resource).or_insert_with(|| Vault::new(resource)).put(fee_tokens);
 
+            // Mint TL badge with the lock as data
+            let lock_data = TimeLockData {
+                resource: resource,
+                amount: available,
+                start_epoch: start_time,
+                unlock_epoch: end_time,
+                schedule: schedule,
+                creator: creator,
+                remaining: available
+            };
+            let lock_id = NonFungibleId::from_u64(self.lock_count);
+            self.lock_count += 1;
+            let tl_badge_resource = self.tl_badge;
             let tl_badge = self.tl_minter_vault.authorize(|| {
-                borrow_resource_manager!(tl_resource_def).mint(1)
+                borrow_resource_manager!(tl_badge_resource).mint_non_fungible(&lock_id, lock_data)
             });
 
-            // store new badge address
-            self.minted.insert(tl_resource_def, (available, end_time));
-
             // put the rest amount of tokens to the locked vault
-            self.locked_xrd.put(lock_tokens);
+            self.locked.entry(resource).or_insert_with(|| Vault::new(resource)).put(lock_tokens);
             tl_badge
         }
 
-        pub fn release(&mut self, tl_badge: Bucket) -> Bucket {
-            let resource_def = tl_badge.resource_address();
-    
-            let mut returns = Bucket::new(RADIX_TOKEN);
-            match self.minted.get(&resource_def) {
-                Some(&value) => {
-                info!("current epoch {}", Runtime::current_epoch());
-                assert!(Runtime::current_epoch() > value.1, "Release time not yet over, wait for a bit longer"); 
-                assert!(value.0 > Decimal::zero(), "Release amount is zero");
+        /// Pays out what unlocked since the last release.
+        /// The TL badge is burned once everything is released, otherwise it is returned with the tokens
+        pub fn release(&mut self, tl_badge: Bucket) -> (Bucket, Option<Bucket>) {
+            assert!(tl_badge.resource_address() == self.tl_badge, "This is not a TL badge");
 
+            // the lock is looked up by the id of the TL badge
+            let lock = tl_badge.non_fungible::<TimeLockData>();
+            let mut lock_data = lock.data();
+            info!("current epoch {}, lock {}", Runtime::current_epoch(), lock.id());
+
+            let to_release = lock_data.releasable(Runtime::current_epoch(), divisibility(lock_data.resource));
+            assert!(to_release > Decimal::zero(), "Nothing unlocked yet, wait for a bit longer");
+            let tokens = self.locked.get_mut(&lock_data.resource).unwrap().take(to_release);
+
+            lock_data.remaining = lock_data.remaining - to_release;
+            if lock_data.remaining == Decimal::zero() {
                 // Burn the TL badge
                 self.tl_minter_vault.authorize(|| {
                     tl_badge.burn();
                 });
-                // update mapping
-                self.minted.remove(&resource_def);
-
-                returns.put(self.locked_xrd.take(value.0));
-                },
-                _ => {
-                    info!("no mints found with provided badge");
-                    std::process::abort();
-                }
+                (tokens, None)
+            } else {
+                self.tl_minter_vault.authorize(|| lock.update_data(lock_data));
+                (tokens, Some(tl_badge))
             }
-            
-            // Return the withdrawn tokens
-            returns
         }
 
-        pub fn claim(&mut self) -> Bucket {
-            self.collected_fees.take_all()
+        /// Pays out everything not released yet before the lock is over.
+        /// The still locked part is charged the early release penalty, which goes to the collected fees
+        pub fn early_release(&mut self, tl_badge: Bucket) -> Bucket {
+            assert!(tl_badge.resource_address() == self.tl_badge, "This is not a TL badge");
+            let penalty_percent = self.early_release_penalty.expect("Locks cannot be released early");
+
+            let lock_data = tl_badge.non_fungible::<TimeLockData>().data();
+            let penalty = lock_data.early_release_penalty(Runtime::current_epoch(), penalty_percent, divisibility(lock_data.resource));
+
+            let mut tokens = self.locked.get_mut(&lock_data.resource).unwrap().take(lock_data.remaining);
+            self.collected_fees.get_mut(&lock_data.resource).unwrap().put(tokens.take(penalty));
+
+            // Burn the TL badge
+            self.tl_minter_vault.authorize(|| {
+                tl_badge.burn();
+            });
+            tokens
+        }
+
+        /// Data of the lock behind a TL badge: resource, amount, start epoch, unlock epoch, schedule, creator and remaining amount
+        pub fn get_lock(&self, lock_id: NonFungibleId) -> (ResourceAddress, Decimal, u64, u64, Schedule, ComponentAddress, Decimal) {
+            let lock_data: TimeLockData = borrow_resource_manager!(self.tl_badge).get_non_fungible_data(&lock_id);
+            (
+                lock_data.resource,
+                lock_data.amount,
+                lock_data.start_epoch,
+                lock_data.unlock_epoch,
+                lock_data.schedule,
+                lock_data.creator,
+                lock_data.remaining
+            )
         }
+
+        /// Amount that `release` would pay out now for a TL badge
+        pub fn get_releasable(&self, lock_id: NonFungibleId) -> Decimal {
+            let lock_data: TimeLockData = borrow_resource_manager!(self.tl_badge).get_non_fungible_data(&lock_id);
+            lock_data.releasable(Runtime::current_epoch(), divisibility(lock_data.resource))
+        }
+
+        pub fn get_tl_badge(&self) -> ResourceAddress {
+            self.tl_badge
+        }
+
+        /// Fee in percents for a lock of `duration` epochs
+        pub fn get_fee_percent(&self, duration: u64) -> Decimal {
+            self.fee_tiers.iter()
+                .rev()
+                .find(|(min_duration, _)| duration >= *min_duration)
+                .map(|(_, fee)| *fee)
+                .unwrap_or(self.fee_percent)
+        }
+
+        /// Penalty that `early_release` would charge now for a TL badge
+        pub fn get_early_release_penalty(&self, lock_id: NonFungibleId) -> Decimal {
+            let penalty_percent = self.early_release_penalty.expect("Locks cannot be released early");
+            let lock_data: TimeLockData = borrow_resource_manager!(self.tl_badge).get_non_fungible_data(&lock_id);
+            lock_data.early_release_penalty(Runtime::current_epoch(), penalty_percent, divisibility(lock_data.resource))
+        }
+
+        /// Only the owner can call this (see the access rules in `new`).
+        /// Tiers are (minimum duration, fee in percents), by increasing duration. Shorter locks pay `fee_percent`
+        pub fn set_fee_tiers(&mut self, fee_tiers: Vec<(u64, Decimal)>) {
+            for (i, (min_duration, fee)) in fee_tiers.iter().enumerate() {
+                assert!(*fee >= Decimal::zero() && *fee < dec!("100"), "The fees must be in [0, 100)");
+                assert!(i == 0 || *min_duration > fee_tiers[i - 1].0, "The tiers must be sorted by increasing duration");
+            }
+            self.fee_tiers = fee_tiers;
+        }
+
+        /// Only the owner can call this (see the access rules in `new`). None disables early releases
+        pub fn set_early_release_penalty(&mut self, penalty_percent: Option<Decimal>) {
+            if let Some(penalty) = penalty_percent {
+                assert!(penalty >= Decimal::zero() && penalty <= dec!("100"), "The penalty must be in [0, 100]");
+            }
+            self.early_release_penalty = penalty_percent;
+        }
+
+        /// Collected fees of every locked resource
+        pub fn claim(&mut self) -> Vec<Bucket> {
+            self.collected_fees.values_mut().map(|fees| fees.take_all()).collect()
+        }
+    }
+}
+
+fn divisibility(resource: ResourceAddress) -> u8 {
+    match borrow_resource_manager!(resource).resource_type() {
+        ResourceType::Fungible { divisibility } => divisibility,
+        ResourceType::NonFungible => panic!("You can only lock fungible tokens"),
+    }
+}
+
+// Amounts are rounded down to what the locked resource can hold, `take` fails on a finer amount
+fn round_down(amount: Decimal, divisibility: u8) -> Decimal {
+    let unit = 10i128.pow(18 - divisibility as u32);
+    Decimal(amount.0 / unit * unit)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // 1000 tokens locked from epoch 0 to 100, nothing released yet
+    fn lock_data(schedule: Schedule) -> TimeLockData {
+        TimeLockData {
+            resource: RADIX_TOKEN,
+            amount: dec!("1000"),
+            start_epoch: 0,
+            unlock_epoch: 100,
+            schedule: schedule,
+            creator: ComponentAddress::from_str("020000000000000000000000000000000000000000000000000002").unwrap(),
+            remaining: dec!("1000"),
+        }
+    }
+
+    #[test]
+    fn test_releasable_excludes_released() {
+        let mut lock = lock_data(Schedule::Linear);
+        assert_eq!(lock.releasable(30, 18), dec!("300"));
+
+        lock.remaining = dec!("700");
+        assert_eq!(lock.releasable(30, 18), dec!("0"));
+        assert_eq!(lock.releasable(50, 18), dec!("200"));
+        assert_eq!(lock.releasable(100, 18), dec!("700"));
+    }
+
+    #[test]
+    fn test_early_release_penalty_shrinks_linearly() {
+        let lock = lock_data(Schedule::Linear);
+        assert_eq!(lock.early_release_penalty(0, dec!("10"), 18), dec!("100"));
+        // 500 still locked, half of the lock time left
+        assert_eq!(lock.early_release_penalty(50, dec!("10"), 18), dec!("25"));
+        assert_eq!(lock.early_release_penalty(100, dec!("10"), 18), dec!("0"));
+    }
+
+    #[test]
+    fn test_early_release_at_unlock_epoch_with_at_end() {
+        let lock = lock_data(Schedule::AtEnd);
+
+        // nothing can be released yet at the unlock epoch, so an early release still pays a penalty
+        assert_eq!(lock.releasable(100, 18), dec!("0"));
+        assert_eq!(lock.early_release_penalty(100, dec!("10"), 18), dec!("100") / dec!("101"));
+
+        assert_eq!(lock.releasable(101, 18), dec!("1000"));
+        assert_eq!(lock.early_release_penalty(101, dec!("10"), 18), dec!("0"));
+    }
+
+    #[test]
+    fn test_low_divisibility() {
+        // 10 tokens that cannot be split
+        let mut lock = lock_data(Schedule::Linear);
+        lock.amount = dec!("10");
+        lock.remaining = dec!("10");
+
+        // 3.3 unlocked
+        assert_eq!(lock.releasable(33, 0), dec!("3"));
+        // 30% of the 7 still locked, for 67% of the lock time
+        assert_eq!(lock.early_release_penalty(33, dec!("30"), 0), dec!("1"));
+
+        lock.remaining = dec!("7");
+        assert_eq!(lock.releasable(66, 0), dec!("3"));
+        lock.remaining = dec!("4");
+        // the last release takes the remainder
+        assert_eq!(lock.releasable(100, 0), dec!("4"));
+
+        // a fee of 2.5% on 100
+        assert_eq!(round_down(dec!("100") * dec!("2.5") / dec!("100"), 0), dec!("2"));
     }
-}
\ No newline at end of file
+}
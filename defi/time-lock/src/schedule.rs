@@ -0,0 +1,98 @@
+use sbor::*;
+use scrypto::prelude::*;
+
+use crate::round_down;
+
+/// How the tokens of a lock unlock between its start and unlock epochs
+#[derive(TypeId, Encode, Decode, Describe, Debug, Clone, PartialEq)]
+pub enum Schedule {
+    /// Everything unlocks after the unlock epoch
+    AtEnd,
+    /// Unlocks linearly, everything is unlocked at the unlock epoch
+    Linear,
+    /// Unlocks in this many equal parts, one at the end of each equal period
+    Steps(u64),
+    /// Nothing unlocks for this many epochs, then what vested linearly so far unlocks at once and the rest keeps vesting linearly
+    CliffLinear(u64),
+}
+
+impl Schedule {
+    pub fn assert_valid(&self, duration: u64) {
+        match self {
+            Schedule::Steps(steps) => assert!(*steps > 0 && *steps <= duration, "The steps must be in [1, duration]"),
+            Schedule::CliffLinear(cliff) => assert!(*cliff <= duration, "The cliff cannot be longer than the lock"),
+            _ => {}
+        }
+    }
+
+    /// Amount of `amount` unlocked at `epoch` for a lock from `start_epoch` to `unlock_epoch`, released or not.
+    /// Rounded down to `divisibility`, everything is unlocked at the end
+    pub fn unlocked(&self, amount: Decimal, divisibility: u8, start_epoch: u64, unlock_epoch: u64, epoch: u64) -> Decimal {
+        let elapsed = epoch.saturating_sub(start_epoch);
+        let duration = unlock_epoch - start_epoch;
+        let unlocked = match self {
+            Schedule::AtEnd if epoch > unlock_epoch => amount,
+            Schedule::AtEnd => Decimal::zero(),
+            _ if epoch >= unlock_epoch => amount,
+            Schedule::Linear => amount * Decimal::from(elapsed) / Decimal::from(duration),
+            Schedule::Steps(steps) => {
+                let steps_done = elapsed * steps / duration;
+                amount * Decimal::from(steps_done) / Decimal::from(*steps)
+            }
+            Schedule::CliffLinear(cliff) if elapsed < *cliff => Decimal::zero(),
+            Schedule::CliffLinear(_) => amount * Decimal::from(elapsed) / Decimal::from(duration),
+        };
+        round_down(unlocked, divisibility)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn unlocked(schedule: Schedule, epoch: u64) -> Decimal {
+        schedule.unlocked(dec!("1000"), 18, 10, 110, epoch)
+    }
+
+    #[test]
+    fn test_at_end() {
+        assert_eq!(unlocked(Schedule::AtEnd, 10), dec!("0"));
+        assert_eq!(unlocked(Schedule::AtEnd, 110), dec!("0"));
+        assert_eq!(unlocked(Schedule::AtEnd, 111), dec!("1000"));
+    }
+
+    #[test]
+    fn test_linear() {
+        assert_eq!(unlocked(Schedule::Linear, 5), dec!("0"));
+        assert_eq!(unlocked(Schedule::Linear, 35), dec!("250"));
+        assert_eq!(unlocked(Schedule::Linear, 110), dec!("1000"));
+        assert_eq!(unlocked(Schedule::Linear, 200), dec!("1000"));
+    }
+
+    #[test]
+    fn test_steps() {
+        assert_eq!(unlocked(Schedule::Steps(4), 34), dec!("0"));
+        assert_eq!(unlocked(Schedule::Steps(4), 35), dec!("250"));
+        assert_eq!(unlocked(Schedule::Steps(4), 109), dec!("750"));
+        assert_eq!(unlocked(Schedule::Steps(4), 110), dec!("1000"));
+    }
+
+    #[test]
+    fn test_cliff_linear() {
+        assert_eq!(unlocked(Schedule::CliffLinear(20), 29), dec!("0"));
+        // what vested during the cliff unlocks at once
+        assert_eq!(unlocked(Schedule::CliffLinear(20), 30), dec!("200"));
+        assert_eq!(unlocked(Schedule::CliffLinear(20), 60), dec!("500"));
+        assert_eq!(unlocked(Schedule::CliffLinear(20), 110), dec!("1000"));
+    }
+
+    #[test]
+    fn test_rounded_to_divisibility() {
+        // 10 tokens that cannot be split, from epoch 0 to 100
+        assert_eq!(Schedule::Linear.unlocked(dec!("10"), 0, 0, 100, 33), dec!("3"));
+        assert_eq!(Schedule::Steps(3).unlocked(dec!("10"), 0, 0, 100, 67), dec!("6"));
+        assert_eq!(Schedule::CliffLinear(20).unlocked(dec!("10"), 0, 0, 100, 25), dec!("2"));
+        assert_eq!(Schedule::Linear.unlocked(dec!("10"), 0, 0, 100, 100), dec!("10"));
+        assert_eq!(Schedule::Linear.unlocked(dec!("10"), 2, 0, 100, 33), dec!("3.3"));
+    }
+}
@@ -15,7 +15,7 @@ package=$(resim publish . | grep Package: | cut -d " " -f4)
 export package
 
 # Create instance of app
-component=$(resim call-function "$package" VirtualXrd new | grep Component: | cut -d " " -f3)
+component=$(resim call-function "$package" VirtualXrd new 1,"$xrd" | grep Component: | cut -d " " -f3)
 export component
 
 #resim call-method "$component" swap_xrd_for_exrd 10,"$xrd"
\ No newline at end of file
@@ -0,0 +1,185 @@
+use sbor::*;
+use scrypto::prelude::*;
+
+// Result of `get_proof_of_reserves`, the wrapped supply must never be larger than the reserves
+#[derive(TypeId, Encode, Decode, Describe)]
+pub struct ProofOfReserves {
+    pub underlying: ResourceAddress,
+    pub wrapped: ResourceAddress,
+    pub reserves: Decimal,
+    pub wrapped_supply: Decimal,
+    pub fully_backed: bool,
+}
+
+blueprint! {
+    struct WrappedTokenFactory {
+        // Prepended to the name and symbol of the underlying, "V" turns XRD into VXRD
+        prefix: String,
+        // Charged in the underlying on both wrap and unwrap, 0 for no fee
+        fee_percent: Decimal,
+        // underlying -> wrapped
+        wrapped_resources: HashMap<ResourceAddress, ResourceAddress>,
+        // wrapped -> underlying
+        underlying_resources: HashMap<ResourceAddress, ResourceAddress>,
+        // Backing of the wrapped tokens, always holds at least the wrapped total supply
+        reserves: HashMap<ResourceAddress, Vault>,
+        // Collected fees, kept apart from the reserves
+        fees: HashMap<ResourceAddress, Vault>,
+        minter_badge: Vault
+    }
+
+    impl WrappedTokenFactory {
+        pub fn new(prefix: String, fee_percent: Decimal) -> (ComponentAddress, Bucket) {
+            assert!(!prefix.is_empty(), "The prefix cannot be empty");
+            assert!(fee_percent >= dec!(0) && fee_percent < dec!(100), "The fee must be in [0, 100)");
+
+            let minter_badge = ResourceBuilder::new_fungible()
+                .divisibility(DIVISIBILITY_NONE)
+                .metadata("name", "Minter Badge")
+                .initial_supply(1);
+
+            let owner_badge = ResourceBuilder::new_fungible()
+                .divisibility(DIVISIBILITY_NONE)
+                .metadata("name", "Wrapped Token Factory Owner Badge")
+                .initial_supply(1);
+
+            let access_rules = AccessRules::new()
+                .method("set_fee", rule!(require(owner_badge.resource_address())))
+                .method("withdraw_fees", rule!(require(owner_badge.resource_address())))
+                .default(rule!(allow_all));
+
+            let component = Self {
+                prefix: prefix,
+                fee_percent: fee_percent,
+                wrapped_resources: HashMap::new(),
+                underlying_resources: HashMap::new(),
+                reserves: HashMap::new(),
+                fees: HashMap::new(),
+                minter_badge: Vault::with_bucket(minter_badge)
+            }
+            .instantiate()
+            .add_access_check(access_rules)
+            .globalize();
+
+            (component, owner_badge)
+        }
+
+        /*
+         * Create the wrapped resource for an underlying fungible resource.
+         * `wrap` does this on the first deposit, so calling it is only needed to learn the address upfront
+         */
+        pub fn create_wrapped(&mut self, underlying: ResourceAddress) -> ResourceAddress {
+            assert!(!self.wrapped_resources.contains_key(&underlying), "This resource is already wrapped");
+            assert!(!self.underlying_resources.contains_key(&underlying), "Wrapped tokens cannot be wrapped again");
+
+            let underlying_manager = borrow_resource_manager!(underlying);
+            let divisibility = divisibility(underlying);
+
+            // copy all metadata, prefixing the name and symbol
+            let mut builder = ResourceBuilder::new_fungible();
+            builder.divisibility(divisibility);
+            for (key, value) in underlying_manager.metadata() {
+                if key == "name" || key == "symbol" {
+                    builder.metadata(key, format!("{}{}", self.prefix, value));
+                } else {
+                    builder.metadata(key, value);
+                }
+            }
+            let wrapped = builder
+                .metadata("underlying", underlying.to_string())
+                .mintable(rule!(require(self.minter_badge.resource_address())), LOCKED)
+                .burnable(rule!(require(self.minter_badge.resource_address())), LOCKED)
+                .no_initial_supply();
+
+            self.wrapped_resources.insert(underlying, wrapped);
+            self.underlying_resources.insert(wrapped, underlying);
+            self.reserves.insert(underlying, Vault::new(underlying));
+            self.fees.insert(underlying, Vault::new(underlying));
+
+            wrapped
+        }
+
+        pub fn wrap(&mut self, mut tokens: Bucket) -> Bucket {
+            let underlying = tokens.resource_address();
+            let wrapped = match self.wrapped_resources.get(&underlying) {
+                Some(wrapped) => *wrapped,
+                None => self.create_wrapped(underlying),
+            };
+
+            let fee = round_down(tokens.amount() * self.fee_percent / 100, divisibility(underlying));
+            self.fees.get_mut(&underlying).unwrap().put(tokens.take(fee));
+
+            let amount = tokens.amount();
+            self.reserves.get_mut(&underlying).unwrap().put(tokens);
+            self.minter_badge.authorize(|| {
+                borrow_resource_manager!(wrapped).mint(amount)
+            })
+        }
+
+        pub fn unwrap(&mut self, wrapped_tokens: Bucket) -> Bucket {
+            let underlying = *self.underlying_resources.get(&wrapped_tokens.resource_address())
+                .expect("These tokens were not wrapped by this factory");
+
+            let amount = wrapped_tokens.amount();
+            let mut tokens = self.reserves.get_mut(&underlying).unwrap().take(amount);
+            self.minter_badge.authorize(|| {
+                wrapped_tokens.burn();
+            });
+
+            let fee = round_down(amount * self.fee_percent / 100, divisibility(underlying));
+            self.fees.get_mut(&underlying).unwrap().put(tokens.take(fee));
+
+            tokens
+        }
+
+        pub fn get_wrapped_resource(&self, underlying: ResourceAddress) -> Option<ResourceAddress> {
+            self.wrapped_resources.get(&underlying).cloned()
+        }
+
+        pub fn get_underlying_resource(&self, wrapped: ResourceAddress) -> Option<ResourceAddress> {
+            self.underlying_resources.get(&wrapped).cloned()
+        }
+
+        pub fn get_proof_of_reserves(&self, underlying: ResourceAddress) -> ProofOfReserves {
+            let wrapped = *self.wrapped_resources.get(&underlying).expect("This resource is not wrapped");
+            let reserves = self.reserves[&underlying].amount();
+            let wrapped_supply = borrow_resource_manager!(wrapped).total_supply();
+
+            ProofOfReserves {
+                underlying: underlying,
+                wrapped: wrapped,
+                reserves: reserves,
+                wrapped_supply: wrapped_supply,
+                fully_backed: reserves >= wrapped_supply
+            }
+        }
+
+        pub fn get_fee(&self) -> Decimal {
+            self.fee_percent
+        }
+
+        // Only the owner can call this (see the access rules in `new`)
+        pub fn set_fee(&mut self, fee_percent: Decimal) {
+            assert!(fee_percent >= dec!(0) && fee_percent < dec!(100), "The fee must be in [0, 100)");
+            self.fee_percent = fee_percent;
+        }
+
+        // Only the owner can call this (see the access rules in `new`)
+        pub fn withdraw_fees(&mut self, underlying: ResourceAddress) -> Bucket {
+            self.fees.get_mut(&underlying).expect("This resource is not wrapped").take_all()
+        }
+    }
+}
+
+fn divisibility(resource: ResourceAddress) -> u8 {
+    match borrow_resource_manager!(resource).resource_type() {
+        ResourceType::Fungible { divisibility } => divisibility,
+        ResourceType::NonFungible => panic!("Only fungible resources can be wrapped"),
+    }
+}
+
+// Fees are rounded down to what the underlying can hold, `take` fails on a finer amount
+fn round_down(amount: Decimal, divisibility: u8) -> Decimal {
+    let unit = 10i128.pow(18 - divisibility as u32);
+    Decimal(amount.0 / unit * unit)
+}
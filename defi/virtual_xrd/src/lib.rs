@@ -1,14 +1,28 @@
+pub mod factory;
+
 use scrypto::prelude::*;
 
 blueprint! {
     struct VirtualXrd {
         vxrd_resource_def: ResourceAddress,
         xrd_vault: Vault,
+        // VXRD minted for the seed, never withdrawn so the supply can't drop to a few units
+        locked_vxrd: Vault,
         minter_badge: Vault
     }
 
     impl VirtualXrd {
-        pub fn new() -> ComponentAddress {
+        /*
+         * The seed of at least 1 XRD is swapped for VXRD which stays locked in the component.
+         * Otherwise the first depositor could mint a tiny supply and deposit yield to inflate the
+         * exchange rate, so the swaps after it would mint nothing and the XRD would go to them
+         */
+        pub fn new(seed: Bucket) -> ComponentAddress {
+            assert!(seed.resource_address() == RADIX_TOKEN,
+                "The seed must be XRD");
+            assert!(seed.amount() >= dec!("1"),
+                "The seed must be at least 1 XRD");
+
             let minter_badge = ResourceBuilder::new_fungible()
                 .divisibility(DIVISIBILITY_NONE)
                 .metadata("name", "Minter Badge")
@@ -21,9 +35,14 @@ blueprint! {
                 .burnable(rule!(require(minter_badge.resource_address())), LOCKED)
                 .no_initial_supply();
 
+            let locked_vxrd = minter_badge.authorize(|| {
+                borrow_resource_manager!(vxrd_resource_def).mint(seed.amount())
+            });
+
             Self {
                 vxrd_resource_def: vxrd_resource_def,
-                xrd_vault: Vault::new(RADIX_TOKEN),
+                xrd_vault: Vault::with_bucket(seed),
+                locked_vxrd: Vault::with_bucket(locked_vxrd),
                 minter_badge: Vault::with_bucket(minter_badge)
             }
             .instantiate().globalize()
@@ -33,7 +52,10 @@ blueprint! {
             assert!(xrd.resource_address() == RADIX_TOKEN,
                 "The tokens for the opportunity must be XRD");
 
-            let amount = xrd.amount();
+            // mint at the current exchange rate, so earlier holders keep the yield deposited so far
+            let amount = xrd.amount() / self.get_exchange_rate();
+            assert!(amount > Decimal::zero(),
+                "The deposit is too small to mint any VXRD");
             self.xrd_vault.put(xrd);
             let vxrd_tokens = self.minter_badge.authorize(|| {
                 borrow_resource_manager!(self.vxrd_resource_def).mint(amount)
@@ -46,7 +68,7 @@ blueprint! {
             assert!(vxrd.resource_address() == self.vxrd_resource_def,
                 "The tokens for the opportunity must be VXRD");
 
-            let amount = vxrd.amount();
+            let amount = vxrd.amount() * self.get_exchange_rate();
             let xrd_tokens = self.xrd_vault.take(amount);
             self.minter_badge.authorize(|| {
                 vxrd.burn();
@@ -54,5 +76,22 @@ blueprint! {
 
             xrd_tokens
         }
+
+        /*
+         * Add XRD to the vault without minting VXRD (e.g. rewards from a staking or lending component).
+         * This raises the exchange rate for every VXRD holder
+         */
+        pub fn deposit_yield(&mut self, xrd: Bucket) {
+            assert!(xrd.resource_address() == RADIX_TOKEN,
+                "The yield must be XRD");
+
+            self.xrd_vault.put(xrd);
+        }
+
+        // XRD per VXRD, starts at 1 and only grows as yield is deposited.
+        // The locked seed keeps the supply above zero
+        pub fn get_exchange_rate(&self) -> Decimal {
+            self.xrd_vault.amount() / borrow_resource_manager!(self.vxrd_resource_def).total_supply()
+        }
     }
 }
@@ -0,0 +1,241 @@
+use amm::factory::ProofOfReserves;
+use radix_engine::ledger::*;
+use radix_engine::transaction::*;
+use scrypto::prelude::*;
+
+struct Account {
+    pk: EcdsaPublicKey,
+    sk: EcdsaPrivateKey,
+    address: ComponentAddress,
+}
+
+struct Setup {
+    account: Account,
+    factory: ComponentAddress,
+    owner_badge: ResourceAddress,
+    // an indivisible resource, so fees need rounding
+    gold: ResourceAddress,
+}
+
+fn execute(executor: &mut TransactionExecutor<InMemorySubstateStore>, account: &Account, builder: &mut TransactionBuilder) -> Receipt {
+    let transaction = builder
+        .call_method_with_all_resources(account.address, "deposit_batch")
+        .build(executor.get_nonce([account.pk]))
+        .sign([&account.sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    println!("{:?}\n", receipt);
+    receipt
+}
+
+fn run(executor: &mut TransactionExecutor<InMemorySubstateStore>, account: &Account, builder: &mut TransactionBuilder) -> Receipt {
+    let receipt = execute(executor, account, builder);
+    assert!(receipt.result.is_ok());
+    receipt
+}
+
+fn balance(executor: &mut TransactionExecutor<InMemorySubstateStore>, account: &Account, resource: ResourceAddress) -> Decimal {
+    let receipt = run(executor, account, TransactionBuilder::new().call_method(account.address, "balance", args![resource]));
+    scrypto_decode(&receipt.outputs[0].raw[..]).unwrap()
+}
+
+// Factory with the prefix "V" and a 0.1% fee, and 10 000 GLD in the account
+fn setup(executor: &mut TransactionExecutor<InMemorySubstateStore>) -> Setup {
+    let (pk, sk, address) = executor.new_account();
+    let account = Account { pk, sk, address };
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let receipt = run(
+        executor,
+        &account,
+        TransactionBuilder::new().call_function(package, "WrappedTokenFactory", "new", args!["V".to_string(), dec!("0.1")]),
+    );
+    let factory = receipt.new_component_addresses[0];
+    // minter and owner badges
+    let owner_badge = receipt.new_resource_addresses[1];
+
+    let mut metadata = HashMap::new();
+    metadata.insert("name".to_string(), "Gold".to_string());
+    metadata.insert("symbol".to_string(), "GLD".to_string());
+    let receipt = run(executor, &account, TransactionBuilder::new().new_badge_fixed(metadata, dec!("10000")));
+    let gold = receipt.new_resource_addresses[0];
+
+    Setup { account, factory, owner_badge, gold }
+}
+
+fn wrap(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Setup, resource: ResourceAddress, amount: Decimal) -> Receipt {
+    execute(
+        executor,
+        &setup.account,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(amount, resource, setup.account.address)
+            .take_from_worktop(resource, |builder, bucket_id| {
+                builder.call_method(setup.factory, "wrap", args![scrypto::resource::Bucket(bucket_id)])
+            }),
+    )
+}
+
+fn unwrap(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &Setup, resource: ResourceAddress, amount: Decimal) -> Receipt {
+    execute(
+        executor,
+        &setup.account,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(amount, resource, setup.account.address)
+            .take_from_worktop(resource, |builder, bucket_id| {
+                builder.call_method(setup.factory, "unwrap", args![scrypto::resource::Bucket(bucket_id)])
+            }),
+    )
+}
+
+#[test]
+fn test_fees_are_rounded_to_the_divisibility() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup(&mut executor);
+
+    // the fee of 1.5 GLD is rounded down to 1
+    let receipt = wrap(&mut executor, &setup, setup.gold, dec!("1500"));
+    assert!(receipt.result.is_ok());
+    let vgold = receipt.new_resource_addresses[0];
+    assert_eq!(balance(&mut executor, &setup.account, vgold), dec!("1499"));
+
+    // the fee of 1.499 GLD is rounded down to 1
+    assert!(unwrap(&mut executor, &setup, vgold, dec!("1499")).result.is_ok());
+    assert_eq!(balance(&mut executor, &setup.account, setup.gold), dec!("9998"));
+
+    let receipt = run(
+        &mut executor,
+        &setup.account,
+        TransactionBuilder::new()
+            .create_proof_from_account_by_amount(dec!("1"), setup.owner_badge, setup.account.address)
+            .call_method(setup.factory, "withdraw_fees", args![setup.gold]),
+    );
+    assert!(receipt.result.is_ok());
+    assert_eq!(balance(&mut executor, &setup.account, setup.gold), dec!("10000"));
+}
+
+#[test]
+fn test_wrapped_tokens_are_fully_backed() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup(&mut executor);
+
+    let receipt = wrap(&mut executor, &setup, RADIX_TOKEN, dec!("1000"));
+    assert!(receipt.result.is_ok());
+    let vxrd = receipt.new_resource_addresses[0];
+    assert_eq!(balance(&mut executor, &setup.account, vxrd), dec!("999"));
+    assert!(unwrap(&mut executor, &setup, vxrd, dec!("500")).result.is_ok());
+
+    let receipt = run(&mut executor, &setup.account, TransactionBuilder::new().call_method(setup.factory, "get_proof_of_reserves", args![RADIX_TOKEN]));
+    let proof: ProofOfReserves = scrypto_decode(&receipt.outputs[0].raw[..]).unwrap();
+    assert_eq!(proof.wrapped, vxrd);
+    assert_eq!(proof.reserves, dec!("499"));
+    assert_eq!(proof.wrapped_supply, dec!("499"));
+    assert!(proof.fully_backed);
+
+    // wrapped tokens can't be wrapped again, and only the owner sets the fee
+    assert!(wrap(&mut executor, &setup, vxrd, dec!("1")).result.is_err());
+    let receipt = execute(&mut executor, &setup.account, TransactionBuilder::new().call_method(setup.factory, "set_fee", args![dec!("0")]));
+    assert!(receipt.result.is_err());
+}
+
+struct VxrdSetup {
+    account: Account,
+    component: ComponentAddress,
+    vxrd: ResourceAddress,
+}
+
+// VirtualXrd seeded with 1 XRD
+fn setup_vxrd(executor: &mut TransactionExecutor<InMemorySubstateStore>) -> VxrdSetup {
+    let (pk, sk, address) = executor.new_account();
+    let account = Account { pk, sk, address };
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let receipt = run(
+        executor,
+        &account,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(dec!("1"), RADIX_TOKEN, account.address)
+            .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+                builder.call_function(package, "VirtualXrd", "new", args![scrypto::resource::Bucket(bucket_id)])
+            }),
+    );
+    let component = receipt.new_component_addresses[0];
+    // minter badge and VXRD resources
+    let vxrd = receipt.new_resource_addresses[1];
+
+    VxrdSetup { account, component, vxrd }
+}
+
+fn call_with_xrd(executor: &mut TransactionExecutor<InMemorySubstateStore>, setup: &VxrdSetup, method: &str, amount: Decimal) -> Receipt {
+    execute(
+        executor,
+        &setup.account,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(amount, RADIX_TOKEN, setup.account.address)
+            .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+                builder.call_method(setup.component, method, args![scrypto::resource::Bucket(bucket_id)])
+            }),
+    )
+}
+
+#[test]
+fn test_seed_below_one_xrd_fails() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (pk, sk, address) = executor.new_account();
+    let account = Account { pk, sk, address };
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let receipt = execute(
+        &mut executor,
+        &account,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(dec!("0.5"), RADIX_TOKEN, account.address)
+            .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+                builder.call_function(package, "VirtualXrd", "new", args![scrypto::resource::Bucket(bucket_id)])
+            }),
+    );
+    assert!(receipt.result.is_err());
+}
+
+#[test]
+fn test_inflated_rate_does_not_take_later_deposits() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup_vxrd(&mut executor);
+
+    // the yield mostly goes to the locked seed, so depositing it to inflate the rate doesn't pay
+    assert!(call_with_xrd(&mut executor, &setup, "deposit_yield", dec!("1000")).result.is_ok());
+
+    let before = balance(&mut executor, &setup.account, RADIX_TOKEN);
+    assert!(call_with_xrd(&mut executor, &setup, "swap_xrd_for_vxrd", dec!("500")).result.is_ok());
+    let vxrd = balance(&mut executor, &setup.account, setup.vxrd);
+    assert!(vxrd > Decimal::zero());
+
+    let receipt = execute(
+        &mut executor,
+        &setup.account,
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(vxrd, setup.vxrd, setup.account.address)
+            .take_from_worktop(setup.vxrd, |builder, bucket_id| {
+                builder.call_method(setup.component, "swap_vxrd_for_xrd", args![scrypto::resource::Bucket(bucket_id)])
+            }),
+    );
+    assert!(receipt.result.is_ok());
+
+    // the round trip only loses the rounding of the exchange rate
+    let lost = before - balance(&mut executor, &setup.account, RADIX_TOKEN);
+    assert!(!lost.is_negative() && lost < dec!("0.000001"), "Lost {} XRD", lost);
+}
+
+#[test]
+fn test_swap_minting_nothing_fails() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let setup = setup_vxrd(&mut executor);
+
+    // with the rate at 2, the smallest amount of XRD buys less than the smallest amount of VXRD
+    assert!(call_with_xrd(&mut executor, &setup, "deposit_yield", dec!("1")).result.is_ok());
+    let receipt = call_with_xrd(&mut executor, &setup, "swap_xrd_for_vxrd", dec!("0.000000000000000001"));
+    assert!(receipt.result.is_err());
+}
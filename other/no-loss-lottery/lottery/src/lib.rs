@@ -1,75 +1,15 @@
 use scrypto::prelude::*;
 
-import! { r#"
-{
-  "package_address": "01c7adee40dd9a16ae290272d0e99835ad5c5e679941d3fb28e608",
-  "blueprint_name": "Staking",
-  "functions": [
-    {
-      "name": "new",
-      "inputs": [
-        {
-          "type": "Custom",
-          "name": "Bucket",
-          "generics": []
-        }
-      ],
-      "output": {
-        "type": "Custom",
-        "name": "ComponentAddress",
-        "generics": []
-      }
-    }
-  ],
-  "methods": [
-    {
-      "name": "new_user",
-      "mutability": "Mutable",
-      "inputs": [],
-      "output": {
-        "type": "Custom",
-        "name": "Bucket",
-        "generics": []
-      }
-    },
-    {
-      "name": "stake",
-      "mutability": "Mutable",
-      "inputs": [
-        {
-          "type": "Custom",
-          "name": "Bucket",
-          "generics": []
-        },
-        {
-          "type": "Custom",
-          "name": "Proof",
-          "generics": []
-        }
-      ],
-      "output": {
-        "type": "Unit"
-      }
-    },
-    {
-      "name": "withdraw",
-      "mutability": "Mutable",
-      "inputs": [
-        {
-          "type": "Custom",
-          "name": "Proof",
-          "generics": []
-        }
-      ],
-      "output": {
-        "type": "Custom",
-        "name": "Bucket",
-        "generics": []
-      }
-    }
-  ]
+use bonding_macros::blueprint_stub;
+
+// the Staking blueprint of ../staking, StakingClient calls an existing Staking component
+#[blueprint_stub(scrypto = "0.4", package_address = "01c7adee40dd9a16ae290272d0e99835ad5c5e679941d3fb28e608")]
+pub trait Staking {
+    fn new(rewards: Bucket) -> ComponentAddress;
+    fn new_user(&mut self) -> Bucket;
+    fn stake(&mut self, staking: Bucket, auth: Proof);
+    fn withdraw(&mut self, auth: Proof) -> Bucket;
 }
-"# }
 
 
 #[derive(NonFungibleData)]
@@ -108,7 +48,7 @@ pub struct LotteryData {
 blueprint! {
     struct Lottery {
         // staking component
-        staking: Staking,
+        staking: StakingClient,
         // admin and minting badges
         admin_badge: ResourceAddress,
         // vault for minting tickets
@@ -165,7 +105,7 @@ blueprint! {
             .no_initial_supply();
 
             // staking setup
-            let staking: Staking = staking_address.into();
+            let staking: StakingClient = staking_address.into();
             // register new user and take the staking user nft (STT)
             let staking_nft = staking.new_user();
 